@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use title_parser::track::Track;
+use title_parser::{CueTrait, ParseOptions};
+
+// Repeats the cues of the example file into a 6000 cue track, about the
+// length of a multi-hour broadcast
+fn huge_vtt() -> String {
+    let example = include_str!("../src/example.vtt");
+    let (header, cues) = example.split_once("\n\n").unwrap();
+    let mut text = format!("{}\n\n", header);
+    for _ in 0..2_000 {
+        text.push_str(cues);
+        text.push_str("\n\n");
+    }
+    text
+}
+
+fn parse_cue(c: &mut Criterion) {
+    let cue = "14\n00:01:14.815 --> 00:01:18.114\n- What?\n- Where are we now?";
+    c.bench_function("cue", |b| b.iter(|| black_box(cue).to_cue().unwrap()));
+}
+
+fn parse_tracks(c: &mut Criterion) {
+    let corpora = [
+        ("small_srt", include_str!("../src/jp.srt").to_string()),
+        ("tag_heavy_vtt", include_str!("../src/jp.vtt").to_string()),
+        ("huge_vtt", huge_vtt()),
+    ];
+    let mut group = c.benchmark_group("track");
+    group.sample_size(10);
+    for (name, text) in corpora.iter() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(*name, |b| b.iter(|| Track::parse(black_box(text)).unwrap()));
+    }
+    let raw = ParseOptions {
+        raw_text: true,
+        ..Default::default()
+    };
+    let (_, text) = &corpora[1];
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("tag_heavy_vtt/raw", |b| {
+        b.iter(|| Track::parse_with(black_box(text), &raw).unwrap())
+    });
+    group.finish();
+}
+
+fn parse_parallel(c: &mut Criterion) {
+    let text = huge_vtt();
+    let options = ParseOptions::default();
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    for threads in [1, 2, 4, 8] {
+        group.bench_function(format!("huge_vtt/{}", threads), |b| {
+            b.iter(|| Track::parse_parallel(black_box(&text), threads, &options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_cue, parse_tracks, parse_parallel);
+criterion_main!(benches);
@@ -0,0 +1,194 @@
+//! renders lint results as burn lists for human QC review
+//!
+//! A burn list shows every flagged cue with its timecodes and issues,
+//! between the cues around it, so a reviewer can jump straight to each
+//! problem and judge it in context. Lists come as CSV for spreadsheets
+//! or as a standalone HTML page.
+
+use crate::lint::Lint;
+use crate::track::Track;
+use crate::write::stamp;
+use std::fmt::Write;
+use std::ops::Range;
+
+// A flagged cue with the cues shown around it
+struct Group {
+    flagged: usize,
+    shown: Range<usize>,
+    issues: Vec<String>,
+}
+
+fn groups(track: &Track, lints: &[Lint], context: usize) -> Vec<Group> {
+    let mut lints: Vec<&Lint> = lints
+        .iter()
+        .filter(|lint| lint.cue < track.cues.len())
+        .collect();
+    lints.sort_by_key(|lint| (lint.cue, lint.line));
+    let mut groups: Vec<Group> = Vec::new();
+    for lint in lints {
+        let issue = format!("line {}: {}", lint.line, lint.kind);
+        match groups.last_mut() {
+            Some(group) if group.flagged == lint.cue => group.issues.push(issue),
+            _ => groups.push(Group {
+                flagged: lint.cue,
+                shown: lint.cue.saturating_sub(context)
+                    ..(lint.cue + context + 1).min(track.cues.len()),
+                issues: vec![issue],
+            }),
+        }
+    }
+    groups
+}
+
+// Quotes a CSV field if it needs it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Track {
+    /// Renders a burn list as CSV, showing `context` cues before and
+    /// after every flagged cue
+    ///
+    /// Every flagged cue starts a group of rows numbered from 1, and
+    /// cues are numbered from 1 as well. Issues are only filled in on
+    /// the flagged row, so context rows are easy to filter out.
+    ///
+    /// ```
+    /// use title_parser::lint::RulePack;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\n¿Qué?\n\n2\n00:00:03,000 --> 00:00:04,000\nNada!\n";
+    /// let track = Track::parse(text).unwrap();
+    /// let lints = track.lint_with(RulePack::Spanish);
+    /// assert_eq!(track.burn_list_csv(&lints, 1), "\
+    /// group,cue,start,end,issues,text
+    /// 1,1,00:00:01.000,00:00:02.000,,¿Qué?
+    /// 1,2,00:00:03.000,00:00:04.000,line 1: ! has no opening mark,Nada!
+    /// ");
+    /// ```
+    pub fn burn_list_csv(&self, lints: &[Lint], context: usize) -> String {
+        let mut out = String::from("group,cue,start,end,issues,text\n");
+        for (number, group) in groups(self, lints, context).iter().enumerate() {
+            for index in group.shown.clone() {
+                let cue = &self.cues[index];
+                let issues = match index == group.flagged {
+                    true => group.issues.join("; "),
+                    false => String::new(),
+                };
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    number + 1,
+                    index + 1,
+                    stamp(cue.start.total_millis(), '.'),
+                    stamp(cue.end.total_millis(), '.'),
+                    csv_field(&issues),
+                    csv_field(&cue.text)
+                );
+            }
+        }
+        out
+    }
+
+    /// Renders a burn list as a standalone HTML page, showing `context`
+    /// cues before and after every flagged cue
+    ///
+    /// Every group can be linked to as `#cue-N`, N being the number of
+    /// the flagged cue counted from 1.
+    pub fn burn_list_html(&self, lints: &[Lint], context: usize) -> String {
+        let mut out = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>Burn list</title>\n",
+            "<style>td { vertical-align: top } .flagged { background: #fdd }</style>\n",
+            "</head>\n<body>\n<table>\n",
+            "<tr><th>Cue</th><th>Start</th><th>End</th><th>Text</th><th>Issues</th></tr>\n",
+        ));
+        for group in groups(self, lints, context) {
+            let _ = writeln!(out, "<tbody id=\"cue-{}\">", group.flagged + 1);
+            for index in group.shown {
+                let cue = &self.cues[index];
+                let (class, issues): (&str, Vec<String>) = match index == group.flagged {
+                    true => (
+                        " class=\"flagged\"",
+                        group.issues.iter().map(|i| html_escape(i)).collect(),
+                    ),
+                    false => ("", Vec::new()),
+                };
+                let text: Vec<String> = cue.text.lines().map(html_escape).collect();
+                let _ = writeln!(
+                    out,
+                    "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    class,
+                    index + 1,
+                    stamp(cue.start.total_millis(), '.'),
+                    stamp(cue.end.total_millis(), '.'),
+                    text.join("<br>"),
+                    issues.join("<br>")
+                );
+            }
+            out.push_str("</tbody>\n");
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::RulePack;
+    use crate::ParseError;
+
+    fn track() -> Result<Track, ParseError> {
+        let mut text = String::new();
+        for (i, line) in ["Bon", "Quoi?", "\"Oui\", & non!", "Non", "Fin"]
+            .iter()
+            .enumerate()
+        {
+            text.push_str(&format!(
+                "{}\n00:00:0{},000 --> 00:00:0{},500\n{}\n\n",
+                i + 1,
+                i,
+                i,
+                line
+            ));
+        }
+        Track::parse(&text)
+    }
+
+    #[test]
+    fn neighbouring_flags_overlap_their_context() -> Result<(), ParseError> {
+        let track = track()?;
+        let lints = track.lint_with(RulePack::French);
+        let csv = track.burn_list_csv(&lints, 1);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(
+            rows[2],
+            "1,2,00:00:01.000,00:00:01.500,line 1: no no-break space before ?,Quoi?"
+        );
+        assert_eq!(rows[5], "2,3,00:00:02.000,00:00:02.500,line 1: no no-break space before !,\"\"\"Oui\"\", & non!\"");
+        Ok(())
+    }
+
+    #[test]
+    fn html_escapes_cue_text() -> Result<(), ParseError> {
+        let track = track()?;
+        let html = track.burn_list_html(&track.lint_with(RulePack::French), 0);
+        assert!(html.contains("<tbody id=\"cue-3\">\n<tr class=\"flagged\"><td>3</td>"));
+        assert!(html.contains("&quot;Oui&quot;, &amp; non!"));
+        assert_eq!(html.matches("<tr").count(), 3);
+        Ok(())
+    }
+}
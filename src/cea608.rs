@@ -0,0 +1,174 @@
+//! validation and transliteration for the CEA-608 (line 21) character set
+//!
+//! Line 21 captions can only display the basic character set plus the
+//! special and extended western european characters defined by the
+//! spec. Anything else has to be replaced before the text can be
+//! encoded as 608 captions.
+
+// Non-ASCII glyphs of the basic set (0x2A, 0x5C, 0x5E-0x60, 0x7B-0x7F)
+static BASIC: &str = "áéíóúç÷Ññ█";
+
+// Special characters (0x11 0x30-0x3F), excluding transparent space
+static SPECIAL: &str = "®°½¿™¢£♪àèâêîôû";
+
+// Extended western european characters (0x12 / 0x13 0x20-0x3F)
+static EXTENDED: &str = "ÁÉÓÚÜü‘¡*’—©℠•“”ÀÂÇÈÊËëÎÏïÔÙùÛ«»\
+                         ÃãÍÌìÒòÕõ{}\\^_|~ÄäÖößÅåØø┌┐└┘¥¤¦";
+
+// Closest displayable equivalents for common unsupported characters
+static FALLBACKS: &[(char, &str)] = &[
+    ('\t', " "),
+    ('`', "'"),
+    ('\u{a0}', " "),
+    ('‚', ","),
+    ('„', "\""),
+    ('‹', "<"),
+    ('›', ">"),
+    ('′', "'"),
+    ('″', "\""),
+    ('–', "-"),
+    ('‐', "-"),
+    ('‑', "-"),
+    ('−', "-"),
+    ('…', "..."),
+    ('·', "."),
+    ('×', "x"),
+    ('€', "EUR"),
+    ('♫', "♪"),
+    ('ā', "a"),
+    ('ă', "a"),
+    ('ą', "a"),
+    ('ć', "c"),
+    ('č', "c"),
+    ('ď', "d"),
+    ('đ', "d"),
+    ('ē', "e"),
+    ('ė', "e"),
+    ('ę', "e"),
+    ('ě', "e"),
+    ('ğ', "g"),
+    ('ī', "i"),
+    ('į', "i"),
+    ('ı', "i"),
+    ('ł', "l"),
+    ('ľ', "l"),
+    ('ń', "n"),
+    ('ň', "n"),
+    ('ō', "o"),
+    ('ő', "o"),
+    ('œ', "oe"),
+    ('ř', "r"),
+    ('ś', "s"),
+    ('š', "s"),
+    ('ş', "s"),
+    ('ť', "t"),
+    ('ū', "u"),
+    ('ů', "u"),
+    ('ű', "u"),
+    ('ų', "u"),
+    ('ý', "y"),
+    ('ÿ', "y"),
+    ('ź', "z"),
+    ('ż', "z"),
+    ('ž', "z"),
+    ('æ', "ae"),
+    ('ð', "d"),
+    ('þ', "th"),
+];
+
+/// Checks whether a character can be displayed by a 608 decoder
+///
+/// Line breaks are accepted since they map to caption rows.
+///
+/// ```
+/// use title_parser::cea608::is_supported;
+///
+/// assert!(is_supported('é'));
+/// assert!(is_supported('♪'));
+/// assert!(!is_supported('ū'));
+/// ```
+pub fn is_supported(c: char) -> bool {
+    if c == '\n' {
+        return true;
+    }
+    // the basic set swaps some printable ASCII for accented letters,
+    // all of which except the grave accent return in the extended set
+    if (' '..='~').contains(&c) {
+        return c != '`';
+    }
+    BASIC.contains(c) || SPECIAL.contains(c) || EXTENDED.contains(c)
+}
+
+/// Lists the characters of the text that a 608 decoder can't display,
+/// along with their character index
+///
+/// ```
+/// use title_parser::cea608::unsupported;
+///
+/// assert_eq!(unsupported("Tōkyō…"), vec![(1, 'ō'), (4, 'ō'), (5, '…')]);
+/// ```
+pub fn unsupported(text: &str) -> Vec<(usize, char)> {
+    text.chars()
+        .enumerate()
+        .filter(|(_, c)| !is_supported(*c))
+        .collect()
+}
+
+/// Result of transliterating text into the 608 character set
+#[derive(Debug, PartialEq, Eq)]
+pub struct Transliteration {
+    /// text containing only supported characters
+    pub text: String,
+    /// characters without an equivalent, left out of `text`
+    pub unmapped: Vec<char>,
+}
+
+/// Replaces unsupported characters with their closest 608 equivalents
+///
+/// Characters that have no sensible equivalent are dropped from the
+/// text and reported in `unmapped`.
+///
+/// ```
+/// use title_parser::cea608::transliterate;
+///
+/// let result = transliterate("Tōkyō… ☃");
+/// assert_eq!(result.text, "Tokyo... ");
+/// assert_eq!(result.unmapped, vec!['☃']);
+/// ```
+pub fn transliterate(text: &str) -> Transliteration {
+    let mut result = Transliteration {
+        text: String::with_capacity(text.len()),
+        unmapped: Vec::new(),
+    };
+    for c in text.chars() {
+        if is_supported(c) {
+            result.text.push(c);
+        } else if let Some((_, fallback)) = FALLBACKS.iter().find(|(from, _)| *from == c) {
+            result.text.push_str(fallback);
+        } else {
+            result.unmapped.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_replaced_by_basic_set() {
+        assert!(!is_supported('`'));
+        assert!(is_supported('*'));
+        assert!(is_supported('~'));
+        assert!(is_supported('A'));
+    }
+
+    #[test]
+    fn fallbacks_are_supported() {
+        for (from, to) in FALLBACKS.iter() {
+            assert!(!is_supported(*from), "{} is already supported", from);
+            assert!(to.chars().all(is_supported), "{} is not supported", to);
+        }
+    }
+}
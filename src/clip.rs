@@ -0,0 +1,164 @@
+//! builds video cut lists from selected cues
+//!
+//! Turns a selection of cues (for example every cue whose text mentions
+//! a word) into padded, merged clip ranges that can be handed to ffmpeg
+//! or any other tool that accepts a JSON cut list.
+
+use crate::Cue;
+
+/// Extra time to include around every selected cue
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Padding {
+    /// milliseconds to include before the cue starts
+    pub pre_ms: u64,
+    /// milliseconds to include after the cue ends
+    pub post_ms: u64,
+}
+
+/// A single range of the source video to keep, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    /// start of the clip
+    pub start_ms: u64,
+    /// end of the clip
+    pub end_ms: u64,
+}
+
+/// Builds padded clip ranges for the given cues
+///
+/// Clips are sorted by start time and any ranges that overlap or touch
+/// after padding is applied are merged into one.
+///
+/// ```
+/// use title_parser::CueTrait;
+/// use title_parser::clip::{clips, Padding};
+///
+/// let a = "00:00:01.000 --> 00:00:02.000\nfirst".to_cue().unwrap();
+/// let b = "00:00:02.500 --> 00:00:03.000\nsecond".to_cue().unwrap();
+/// let padding = Padding { pre_ms: 500, post_ms: 250 };
+/// let clips = clips(&[a, b], padding);
+/// assert_eq!(clips.len(), 1);
+/// assert_eq!((clips[0].start_ms, clips[0].end_ms), (500, 3250));
+/// ```
+pub fn clips<'a, I>(cues: I, padding: Padding) -> Vec<Clip>
+where
+    I: IntoIterator<Item = &'a Cue>,
+{
+    let mut ranges: Vec<Clip> = cues
+        .into_iter()
+        .map(|cue| Clip {
+            start_ms: cue.start.total_millis().saturating_sub(padding.pre_ms),
+            end_ms: cue.end.total_millis().saturating_add(padding.post_ms),
+        })
+        .collect();
+    ranges.sort_by_key(|clip| clip.start_ms);
+
+    let mut merged: Vec<Clip> = Vec::with_capacity(ranges.len());
+    for clip in ranges {
+        match merged.last_mut() {
+            Some(last) if clip.start_ms <= last.end_ms => {
+                last.end_ms = last.end_ms.max(clip.end_ms);
+            }
+            _ => merged.push(clip),
+        }
+    }
+    merged
+}
+
+/// Renders clips as an ffmpeg concat demuxer script
+///
+/// The output can be saved to a file and used with
+/// `ffmpeg -f concat -safe 0 -i list.txt -c copy out.mp4` to trim
+/// `input` to the clips and join them back together.
+///
+/// ```
+/// use title_parser::clip::{ffmpeg_concat, Clip};
+///
+/// let clips = [Clip { start_ms: 500, end_ms: 3250 }];
+/// let script = ffmpeg_concat("movie.mp4", &clips);
+/// assert_eq!(script, "ffconcat version 1.0\nfile 'movie.mp4'\ninpoint 0.500\noutpoint 3.250\n");
+/// ```
+pub fn ffmpeg_concat(input: &str, clips: &[Clip]) -> String {
+    let mut script = String::from("ffconcat version 1.0\n");
+    let path = input.replace('\'', r"'\''");
+    for clip in clips {
+        script.push_str(&format!("file '{}'\n", path));
+        script.push_str(&format!("inpoint {}\n", seconds(clip.start_ms)));
+        script.push_str(&format!("outpoint {}\n", seconds(clip.end_ms)));
+    }
+    script
+}
+
+/// Renders clips as a JSON cut list of `start` / `end` seconds
+///
+/// ```
+/// use title_parser::clip::{json_cut_list, Clip};
+///
+/// let clips = [Clip { start_ms: 500, end_ms: 3250 }];
+/// assert_eq!(json_cut_list(&clips), r#"[{"start":0.500,"end":3.250}]"#);
+/// ```
+pub fn json_cut_list(clips: &[Clip]) -> String {
+    let entries: Vec<String> = clips
+        .iter()
+        .map(|clip| {
+            format!(
+                r#"{{"start":{},"end":{}}}"#,
+                seconds(clip.start_ms),
+                seconds(clip.end_ms)
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CueTrait;
+    use crate::ParseError;
+
+    #[test]
+    fn clips_keep_separate_ranges() -> Result<(), ParseError> {
+        let a = "00:00:01.000 --> 00:00:02.000\nfirst".to_cue()?;
+        let b = "00:00:10.000 --> 00:00:11.000\nsecond".to_cue()?;
+        let clips = clips(&[b, a], Padding::default());
+        let expected = vec![
+            Clip {
+                start_ms: 1000,
+                end_ms: 2000,
+            },
+            Clip {
+                start_ms: 10000,
+                end_ms: 11000,
+            },
+        ];
+        assert_eq!(clips, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn clips_padding_saturates_at_zero() -> Result<(), ParseError> {
+        let a = "00:00:00.200 --> 00:00:01.000\nfirst".to_cue()?;
+        let padding = Padding {
+            pre_ms: 1000,
+            post_ms: 0,
+        };
+        assert_eq!(clips(&[a], padding)[0].start_ms, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn clips_padding_saturates_at_max() -> Result<(), ParseError> {
+        let a = "00:00:00.200 --> 00:00:01.000\nfirst".to_cue()?;
+        let padding = Padding {
+            pre_ms: 0,
+            post_ms: u64::MAX,
+        };
+        assert_eq!(clips(&[a], padding)[0].end_ms, u64::MAX);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! exports the scripts audio description writers work from
+//!
+//! Description has to fit around the dialogue, so writers need to know
+//! exactly when nobody is speaking and for how long. The script lists
+//! the dialogue cues in order with every gap between them.
+
+use crate::track::Track;
+use crate::write::stamp;
+use std::fmt::Write;
+
+/// A stretch of time without dialogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// when the gap starts, in milliseconds
+    pub start_ms: u64,
+    /// how long the gap lasts, in milliseconds
+    pub duration_ms: u64,
+}
+
+impl Track {
+    /// Finds the gaps of at least `min_gap_ms` between cues, including
+    /// the one before the first cue
+    ///
+    /// Overlapping cues count as continuous dialogue. Nothing is known
+    /// about the programme after the last cue, so no gap is reported
+    /// there.
+    ///
+    /// ```
+    /// use title_parser::describe::Gap;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:02,000 --> 00:00:04,000\nHello\n\n2\n00:00:09,500 --> 00:00:11,000\nWorld\n";
+    /// let gaps = Track::parse(text).unwrap().description_gaps(1_000);
+    /// assert_eq!(gaps, vec![
+    ///     Gap { start_ms: 0, duration_ms: 2_000 },
+    ///     Gap { start_ms: 4_000, duration_ms: 5_500 },
+    /// ]);
+    /// ```
+    pub fn description_gaps(&self, min_gap_ms: u64) -> Vec<Gap> {
+        let mut times: Vec<(u64, u64)> = self
+            .cues
+            .iter()
+            .map(|cue| (cue.start.total_millis(), cue.end.total_millis()))
+            .collect();
+        times.sort_unstable();
+        let mut gaps = Vec::new();
+        // end of the dialogue heard so far
+        let mut spoken = 0;
+        for (start, end) in times {
+            if start > spoken && start - spoken >= min_gap_ms {
+                gaps.push(Gap {
+                    start_ms: spoken,
+                    duration_ms: start - spoken,
+                });
+            }
+            spoken = spoken.max(end);
+        }
+        gaps
+    }
+
+    /// Renders an audio description script: every cue and every gap of
+    /// at least `min_gap_ms`, in time order, one per row of start time,
+    /// duration and dialogue
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:02,000 --> 00:00:04,000\nHello\nthere\n\n2\n00:00:09,500 --> 00:00:11,000\nWorld\n";
+    /// let script = Track::parse(text).unwrap().audio_description_script(1_000);
+    /// assert_eq!(script, "\
+    /// 00:00:00.000    +2.000  [GAP]
+    /// 00:00:02.000    +2.000  Hello
+    ///                         there
+    /// 00:00:04.000    +5.500  [GAP]
+    /// 00:00:09.500    +1.500  World
+    /// ");
+    /// ```
+    pub fn audio_description_script(&self, min_gap_ms: u64) -> String {
+        let mut rows: Vec<(u64, u64, &str)> = self
+            .description_gaps(min_gap_ms)
+            .into_iter()
+            .map(|gap| (gap.start_ms, gap.duration_ms, "[GAP]"))
+            .collect();
+        rows.extend(self.cues.iter().map(|cue| {
+            let start = cue.start.total_millis();
+            let end = cue.end.total_millis().max(start);
+            (start, end - start, cue.text.as_str())
+        }));
+        rows.sort_by_key(|&(start, _, _)| start);
+
+        let mut out = String::new();
+        for (start, duration, text) in rows {
+            let mut lines = text.lines();
+            let duration = format!("+{}.{:03}", duration / 1000, duration % 1000);
+            let first = lines.next().unwrap_or("");
+            let _ = writeln!(out, "{}  {:>8}  {}", stamp(start, '.'), duration, first);
+            for line in lines {
+                let _ = writeln!(out, "{:24}{}", "", line);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseError;
+
+    #[test]
+    fn overlapping_dialogue_has_no_gap() -> Result<(), ParseError> {
+        let text = "1\n00:00:00,000 --> 00:00:05,000\nA\n\n2\n00:00:01,000 --> 00:00:02,000\nB\n\n3\n00:00:05,400 --> 00:00:06,000\nC\n\n4\n00:00:08,000 --> 00:00:09,000\nD\n";
+        let track = Track::parse(text)?;
+        assert_eq!(
+            track.description_gaps(500),
+            vec![Gap {
+                start_ms: 6_000,
+                duration_ms: 2_000
+            }]
+        );
+        assert_eq!(track.description_gaps(0).len(), 2);
+        Ok(())
+    }
+}
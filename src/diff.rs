@@ -0,0 +1,299 @@
+//! word level differences between cue texts
+//!
+//! Used by review tools to show what a correction pass changed in each
+//! cue and roughly when in the cue it was said.
+
+use crate::timecode::TimeCodeTrait;
+use crate::Cue;
+
+/// Kind of edit turning the old words into the new ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// words only present in the new text
+    Insert,
+    /// words only present in the old text
+    Delete,
+    /// old words replaced by new words
+    Replace,
+}
+
+/// A single word level edit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// kind of edit
+    pub kind: ChangeKind,
+    /// index of the first affected word in the old text
+    pub old_index: usize,
+    /// index of the first affected word in the new text
+    pub new_index: usize,
+    /// removed words, empty for inserts
+    pub old: Vec<String>,
+    /// added words, empty for deletes
+    pub new: Vec<String>,
+}
+
+/// A word level edit along with the part of the cue it falls in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedChange {
+    /// the edit itself
+    pub change: Change,
+    /// estimated time the affected words start, in milliseconds
+    pub start_ms: u64,
+    /// estimated time the affected words end, in milliseconds
+    pub end_ms: u64,
+}
+
+/// Computes the word level edits turning `old` into `new`
+///
+/// Words are separated by whitespace, so line breaks don't count as
+/// changes.
+///
+/// ```
+/// use title_parser::diff::{text_diff, ChangeKind};
+///
+/// let changes = text_diff("they are after the bugs", "they're after the bats");
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].kind, ChangeKind::Replace);
+/// assert_eq!(changes[0].old, vec!["they", "are"]);
+/// assert_eq!(changes[0].new, vec!["they're"]);
+/// assert_eq!(changes[1].old, vec!["bugs"]);
+/// ```
+pub fn text_diff(old: &str, new: &str) -> Vec<Change> {
+    let a: Vec<&str> = old.split_whitespace().collect();
+    let b: Vec<&str> = new.split_whitespace().collect();
+
+    // lengths of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (start_i, start_j) = (i, j);
+        while (i < a.len() || j < b.len()) && !(i < a.len() && j < b.len() && a[i] == b[j]) {
+            if j >= b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        let old: Vec<String> = a[start_i..i].iter().map(|w| w.to_string()).collect();
+        let new: Vec<String> = b[start_j..j].iter().map(|w| w.to_string()).collect();
+        let kind = match (old.is_empty(), new.is_empty()) {
+            (true, _) => ChangeKind::Insert,
+            (_, true) => ChangeKind::Delete,
+            _ => ChangeKind::Replace,
+        };
+        changes.push(Change {
+            kind,
+            old_index: start_i,
+            new_index: start_j,
+            old,
+            new,
+        });
+    }
+    changes
+}
+
+/// Computes the word level edits between two versions of a cue,
+/// attributing each edit to a time range
+///
+/// Words are timed by the inline `<hh:mm:ss.ttt>` timestamps of
+/// WebVTT karaoke cues, which only cues parsed with
+/// `ParseOptions::raw_text` keep. Words before the first timestamp
+/// start with the cue. Without timestamps, times are estimated by
+/// spreading the words evenly over the cue's duration. Markup is left
+/// out of the comparison. Inserted and replaced words are timed
+/// against the new cue, deleted words against the old one.
+///
+/// ```
+/// use title_parser::{CueTrait, ParseOptions};
+/// use title_parser::diff::cue_text_diff;
+///
+/// let old = "00:00:10.000 --> 00:00:14.000\none two three four".to_cue().unwrap();
+/// let new = "00:00:10.000 --> 00:00:14.000\none two three five".to_cue().unwrap();
+/// let changes = cue_text_diff(&old, &new);
+/// assert_eq!((changes[0].start_ms, changes[0].end_ms), (13_000, 14_000));
+///
+/// let raw = ParseOptions { raw_text: true, ..Default::default() };
+/// let old = "00:00:10.000 --> 00:00:14.000\none <00:00:10.500>two <00:00:13.500>three".to_cue_with(&raw).unwrap();
+/// let new = "00:00:10.000 --> 00:00:14.000\none <00:00:10.500>too <00:00:13.500>three".to_cue_with(&raw).unwrap();
+/// let changes = cue_text_diff(&old, &new);
+/// assert_eq!(changes[0].change.new, vec!["too"]);
+/// assert_eq!((changes[0].start_ms, changes[0].end_ms), (10_500, 13_500));
+/// ```
+pub fn cue_text_diff(old: &Cue, new: &Cue) -> Vec<TimedChange> {
+    let (old_words, new_words) = (TimedWords::of(old), TimedWords::of(new));
+    text_diff(&old_words.words.join(" "), &new_words.words.join(" "))
+        .into_iter()
+        .map(|change| {
+            let (words, index, count) = match change.kind {
+                ChangeKind::Delete => (&old_words, change.old_index, change.old.len()),
+                _ => (&new_words, change.new_index, change.new.len()),
+            };
+            let (start_ms, end_ms) = words.span(index, count);
+            TimedChange {
+                change,
+                start_ms,
+                end_ms,
+            }
+        })
+        .collect()
+}
+
+// The words of a cue without markup, with when each starts if the cue
+// has inline timestamps
+struct TimedWords {
+    words: Vec<String>,
+    starts: Option<Vec<u64>>,
+    start: u64,
+    end: u64,
+}
+
+impl TimedWords {
+    fn of(cue: &Cue) -> TimedWords {
+        let start = cue.start.total_millis();
+        let end = cue.end.total_millis().max(start);
+        let (mut words, mut starts) = (Vec::new(), Vec::new());
+        let mut now = start;
+        let mut timed = false;
+        for token in cue.text.split_whitespace() {
+            let mut word = String::new();
+            let mut rest = token;
+            while !rest.is_empty() {
+                let tag = rest.find('<').map(|open| (open, rest[open..].find('>')));
+                let (open, close) = match tag {
+                    Some((open, Some(close))) => (open, open + close),
+                    _ => {
+                        word.push_str(rest);
+                        break;
+                    }
+                };
+                word.push_str(&rest[..open]);
+                if let Ok(tc) = rest[open + 1..close].to_timecode() {
+                    // a timestamp ends the word before it, like a space
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                        starts.push(now);
+                    }
+                    now = tc.total_millis().clamp(start, end);
+                    timed = true;
+                }
+                rest = &rest[close + 1..];
+            }
+            if !word.is_empty() {
+                words.push(word);
+                starts.push(now);
+            }
+        }
+        TimedWords {
+            words,
+            starts: timed.then_some(starts),
+            start,
+            end,
+        }
+    }
+
+    // When words `index..index + count` are displayed, going by the
+    // timestamps or else estimated
+    fn span(&self, index: usize, count: usize) -> (u64, u64) {
+        let (start, end) = (self.start, self.end);
+        if let Some(starts) = &self.starts {
+            let at = |i: usize| starts.get(i).copied().unwrap_or(end);
+            return (at(index).min(end), at(index + count).max(at(index)));
+        }
+        let total = self.words.len();
+        if total == 0 {
+            return (start, end);
+        }
+        let at = |words: usize| start + (end - start) * words as u64 / total as u64;
+        (at(index), at(index + count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CueTrait;
+    use crate::ParseError;
+
+    #[test]
+    fn text_diff_inserts_and_deletes() {
+        let changes = text_diff("a b c", "x a c d");
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    kind: ChangeKind::Insert,
+                    old_index: 0,
+                    new_index: 0,
+                    old: vec![],
+                    new: vec!["x".to_string()],
+                },
+                Change {
+                    kind: ChangeKind::Delete,
+                    old_index: 1,
+                    new_index: 2,
+                    old: vec!["b".to_string()],
+                    new: vec![],
+                },
+                Change {
+                    kind: ChangeKind::Insert,
+                    old_index: 3,
+                    new_index: 3,
+                    old: vec![],
+                    new: vec!["d".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn text_diff_identical() {
+        assert!(text_diff("same\nwords", "same words").is_empty());
+    }
+
+    #[test]
+    fn cue_text_diff_deletes_use_old_timing() -> Result<(), ParseError> {
+        let old = "00:00:00.000 --> 00:00:02.000\nkeep drop".to_cue()?;
+        let new = "00:00:05.000 --> 00:00:06.000\nkeep".to_cue()?;
+        let changes = cue_text_diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].start_ms, changes[0].end_ms), (1000, 2000));
+        Ok(())
+    }
+
+    #[test]
+    fn cue_text_diff_uses_inline_timestamps() -> Result<(), ParseError> {
+        let raw = crate::ParseOptions {
+            raw_text: true,
+            ..Default::default()
+        };
+        let old =
+            "00:00:10.000 --> 00:00:20.000\n<c>one</c> <00:00:11.000>two<00:00:15.000> three four"
+                .to_cue_with(&raw)?;
+        let new = "00:00:10.000 --> 00:00:20.000\none <00:00:11.000>too<00:00:15.000> three"
+            .to_cue_with(&raw)?;
+        let changes = cue_text_diff(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].change.old, vec!["two"]);
+        // evenly spread, "two" would start at 12.5s
+        assert_eq!((changes[0].start_ms, changes[0].end_ms), (11_000, 15_000));
+        assert_eq!(changes[1].change.old, vec!["four"]);
+        assert_eq!((changes[1].start_ms, changes[1].end_ms), (15_000, 20_000));
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! detects the text encoding of raw subtitle bytes and decodes them
+
+use crate::track::Confidence;
+use crate::Encoding;
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+// Decoded text along with the name of the encoding it was stored in and
+// how sure detection was about it
+pub(crate) struct Decoded<'a> {
+    pub(crate) text: Cow<'a, str>,
+    pub(crate) encoding: &'static str,
+    pub(crate) confidence: Confidence,
+}
+
+// Decodes UTF-8 or UTF-16 input, with or without a byte order mark.
+// With the `encoding_rs` feature, input that is neither falls back to
+// the hinted encoding, or else to Shift_JIS or windows-1252.
+pub(crate) fn decode<'a>(bytes: &'a [u8], choice: &Encoding) -> Result<Decoded<'a>, String> {
+    let hint = match choice {
+        Encoding::Detect => None,
+        Encoding::Hint(label) => Some(label.as_str()),
+        Encoding::Override(label) => return forced(bytes, label),
+    };
+    let (text, encoding, confidence) = if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        (utf8(rest)?, "UTF-8", Confidence::Certain)
+    } else if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        (
+            utf16(rest, u16::from_le_bytes)?,
+            "UTF-16LE",
+            Confidence::Certain,
+        )
+    } else if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        (
+            utf16(rest, u16::from_be_bytes)?,
+            "UTF-16BE",
+            Confidence::Certain,
+        )
+    } else {
+        // subtitle text is mostly ASCII digits and punctuation, so UTF-16
+        // without a BOM shows up as a zero in every other byte
+        match zero_parity(bytes) {
+            Some(1) => (
+                utf16(bytes, u16::from_le_bytes)?,
+                "UTF-16LE",
+                Confidence::High,
+            ),
+            Some(0) => (
+                utf16(bytes, u16::from_be_bytes)?,
+                "UTF-16BE",
+                Confidence::High,
+            ),
+            _ => return legacy(bytes, hint),
+        }
+    };
+    Ok(Decoded {
+        text,
+        encoding,
+        confidence,
+    })
+}
+
+// Decodes input with the encoding the caller asked for, skipping
+// detection entirely
+fn forced<'a>(bytes: &'a [u8], label: &str) -> Result<Decoded<'a>, String> {
+    let (text, encoding) = match label.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => (
+            utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes))?,
+            "UTF-8",
+        ),
+        "utf-16le" => (
+            utf16(
+                bytes.strip_prefix(UTF16LE_BOM).unwrap_or(bytes),
+                u16::from_le_bytes,
+            )?,
+            "UTF-16LE",
+        ),
+        "utf-16be" => (
+            utf16(
+                bytes.strip_prefix(UTF16BE_BOM).unwrap_or(bytes),
+                u16::from_be_bytes,
+            )?,
+            "UTF-16BE",
+        ),
+        _ => labelled(bytes, label)?,
+    };
+    Ok(Decoded {
+        text,
+        encoding,
+        confidence: Confidence::Certain,
+    })
+}
+
+// Decodes UTF-8 input, reporting pure ASCII as certain since every
+// candidate encoding would read it the same way
+fn detected_utf8(text: &str) -> Decoded<'_> {
+    Decoded {
+        text: Cow::Borrowed(text),
+        encoding: "UTF-8",
+        confidence: if text.is_ascii() {
+            Confidence::Certain
+        } else {
+            Confidence::High
+        },
+    }
+}
+
+#[cfg(not(feature = "encoding_rs"))]
+fn labelled<'a>(_: &'a [u8], label: &str) -> Result<(Cow<'a, str>, &'static str), String> {
+    Err(format!("unsupported encoding {}", label))
+}
+
+#[cfg(feature = "encoding_rs")]
+fn labelled<'a>(bytes: &'a [u8], label: &str) -> Result<(Cow<'a, str>, &'static str), String> {
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("unsupported encoding {}", label))?;
+    let text = encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or_else(|| format!("input is not valid {}", encoding.name()))?;
+    Ok((Cow::Owned(text.into_owned()), encoding.name()))
+}
+
+#[cfg(not(feature = "encoding_rs"))]
+fn legacy<'a>(bytes: &'a [u8], _hint: Option<&str>) -> Result<Decoded<'a>, String> {
+    std::str::from_utf8(bytes)
+        .map(detected_utf8)
+        .map_err(|_| "input is not valid UTF-8".to_string())
+}
+
+#[cfg(feature = "encoding_rs")]
+fn legacy<'a>(bytes: &'a [u8], hint: Option<&str>) -> Result<Decoded<'a>, String> {
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(detected_utf8(text));
+    }
+    if let Some(label) = hint {
+        let (text, encoding) = labelled(bytes, label)?;
+        return Ok(Decoded {
+            text,
+            encoding,
+            confidence: Confidence::High,
+        });
+    }
+    // single byte text often happens to be valid Shift_JIS too, so only
+    // trust it when the result actually contains kana
+    if let Some(text) = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes) {
+        if text.chars().any(|c| ('\u{3041}'..='\u{30ff}').contains(&c)) {
+            return Ok(Decoded {
+                text: Cow::Owned(text.into_owned()),
+                encoding: SHIFT_JIS.name(),
+                confidence: Confidence::Low,
+            });
+        }
+    }
+    // windows-1252 is a superset of latin-1 and maps every byte
+    let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+    Ok(Decoded {
+        text: Cow::Owned(text.into_owned()),
+        encoding: WINDOWS_1252.name(),
+        confidence: Confidence::Low,
+    })
+}
+
+fn utf8(bytes: &[u8]) -> Result<Cow<'_, str>, String> {
+    std::str::from_utf8(bytes)
+        .map(Cow::Borrowed)
+        .map_err(|_| "input is not valid UTF-8".to_string())
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<Cow<'static, str>, String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err("input is not valid UTF-16".to_string());
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units)
+        .map(Cow::Owned)
+        .map_err(|_| "input is not valid UTF-16".to_string())
+}
+
+// Returns the byte parity (0 = even, 1 = odd) holding most zero bytes,
+// if zeros are common enough to suggest UTF-16
+fn zero_parity(bytes: &[u8]) -> Option<usize> {
+    let mut zeros = [0usize; 2];
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte == 0 {
+            zeros[i % 2] += 1;
+        }
+    }
+    let half = bytes.len() / 2;
+    (0..2).find(|&parity| half > 0 && zeros[parity] * 2 > half)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_auto(bytes: &[u8]) -> Result<Decoded<'_>, String> {
+        decode(bytes, &Encoding::Detect)
+    }
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom {
+            UTF16LE_BOM.to_vec()
+        } else {
+            Vec::new()
+        };
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_boms() -> Result<(), String> {
+        let decoded = decode_auto(b"\xEF\xBB\xBF1\n")?;
+        assert_eq!((decoded.text.as_ref(), decoded.encoding), ("1\n", "UTF-8"));
+        let bytes = utf16le("1\nガンダム", true);
+        let decoded = decode_auto(&bytes)?;
+        assert_eq!(
+            (decoded.text.as_ref(), decoded.encoding),
+            ("1\nガンダム", "UTF-16LE")
+        );
+        let decoded = decode_auto(b"\xFE\xFF\x00\x31")?;
+        assert_eq!((decoded.text.as_ref(), decoded.encoding), ("1", "UTF-16BE"));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_with_override() -> Result<(), String> {
+        let forced = Encoding::Override("UTF-16LE".to_string());
+        let bytes = utf16le("1\n", true);
+        let decoded = decode(&bytes, &forced)?;
+        assert_eq!(decoded.text, "1\n");
+        assert_eq!(decoded.confidence, Confidence::Certain);
+        assert!(decode(b"1\n", &Encoding::Override("klingon".to_string())).is_err());
+        assert_eq!(decode_auto(b"1\n")?.confidence, Confidence::Certain);
+        assert_eq!(decode_auto("ü".as_bytes())?.confidence, Confidence::High);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_utf16_without_bom() -> Result<(), String> {
+        let text = "1\n00:00:12,584 --> 00:00:14,876\n最終防衛ライン";
+        assert_eq!(decode_auto(&utf16le(text, false))?.text, text);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "encoding_rs"))]
+    #[test]
+    fn decode_rejects_garbage() {
+        assert!(decode_auto(b"\xFF\xFF\xC3\x28").is_err());
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn decode_legacy_encodings() -> Result<(), String> {
+        let decoded = decode_auto(b"Caf\xe9 cr\xe8me")?;
+        assert_eq!(decoded.text, "Café crème");
+        assert_eq!(decoded.encoding, "windows-1252");
+        // "ガンダム発進" in Shift_JIS
+        let decoded = decode_auto(b"\x83\x4b\x83\x93\x83\x5f\x83\x80\x94\xad\x90\x69")?;
+        assert_eq!(decoded.text, "ガンダム発進");
+        assert_eq!(decoded.encoding, "Shift_JIS");
+        assert_eq!(decoded.confidence, Confidence::Low);
+        Ok(())
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn decode_with_central_european_hint() -> Result<(), String> {
+        // "Žluťoučký kůň" in windows-1250
+        let bytes = b"\x8elu\x9dou\xe8k\xfd k\xf9\xf2";
+        let hint = Encoding::Hint("windows-1250".to_string());
+        let decoded = decode(bytes, &hint)?;
+        assert_eq!(decoded.text, "Žluťoučký kůň");
+        assert_eq!(
+            (decoded.encoding, decoded.confidence),
+            ("windows-1250", Confidence::High)
+        );
+        // hints only apply when the input isn't valid UTF-8
+        assert_eq!(decode("kůň".as_bytes(), &hint)?.encoding, "UTF-8");
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! controls how HTML character references in cue text are handled
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+
+/// What to do with an entity found in cue text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityAction {
+    /// replace the entity with the character it stands for
+    Decode,
+    /// remove the entity from the text
+    Strip,
+    /// leave the entity in the text exactly as written
+    Preserve,
+}
+
+/// Per-class treatment of entities in cue text
+///
+/// Entities are grouped into formatting entities (`&amp;`, `&lt;`,
+/// `&gt;`, `&nbsp;`), directionality marks (`&lrm;`, `&rlm;`) and
+/// numeric references (`&#233;`, `&#xE9;`). Named entities outside
+/// these groups are always preserved.
+///
+/// The default strips formatting entities and directionality marks and
+/// preserves numeric references.
+///
+/// ```
+/// use title_parser::{CueTrait, ParseOptions};
+/// use title_parser::entity::{EntityAction, EntityPolicy};
+///
+/// let options = ParseOptions {
+///     entities: EntityPolicy {
+///         formatting: EntityAction::Decode,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let text = "00:01:14.815 --> 00:01:18.114\nTom &amp; Jerry&lrm;";
+/// let cue = text.to_cue_with(&options).unwrap();
+/// assert_eq!(cue.text, "Tom & Jerry");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityPolicy {
+    /// treatment of `&amp;`, `&lt;`, `&gt;` and `&nbsp;`
+    pub formatting: EntityAction,
+    /// treatment of `&lrm;` and `&rlm;`
+    pub directionality: EntityAction,
+    /// treatment of decimal and hexadecimal references
+    pub numeric: EntityAction,
+}
+
+impl Default for EntityPolicy {
+    fn default() -> Self {
+        EntityPolicy {
+            formatting: EntityAction::Strip,
+            directionality: EntityAction::Strip,
+            numeric: EntityAction::Preserve,
+        }
+    }
+}
+
+static ENTITY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]+);").expect("failed to compile regex")
+});
+
+// Applies the policy to every entity in the input
+pub(crate) fn apply<'t>(input: &'t str, policy: &EntityPolicy) -> Cow<'t, str> {
+    ENTITY.replace_all(input, |caps: &Captures| {
+        let entity = caps.get(0).unwrap().as_str();
+        let name = caps.get(1).unwrap().as_str();
+        let (action, decoded) = match name {
+            "amp" => (policy.formatting, Some('&')),
+            "lt" => (policy.formatting, Some('<')),
+            "gt" => (policy.formatting, Some('>')),
+            "nbsp" => (policy.formatting, Some('\u{a0}')),
+            "lrm" => (policy.directionality, Some('\u{200e}')),
+            "rlm" => (policy.directionality, Some('\u{200f}')),
+            _ if name.starts_with('#') => (policy.numeric, decode_numeric(&name[1..])),
+            _ => (EntityAction::Preserve, None),
+        };
+        match (action, decoded) {
+            (EntityAction::Decode, Some(c)) => c.to_string(),
+            (EntityAction::Strip, _) => String::new(),
+            _ => entity.to_string(),
+        }
+    })
+}
+
+fn decode_numeric(reference: &str) -> Option<char> {
+    let code = match reference.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => reference.parse().ok()?,
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_default_policy() {
+        let input = "&lrm;Tom &amp; Jerry&#233;";
+        assert_eq!(apply(input, &EntityPolicy::default()), "Tom  Jerry&#233;");
+    }
+
+    #[test]
+    fn apply_decode_all() {
+        let policy = EntityPolicy {
+            formatting: EntityAction::Decode,
+            directionality: EntityAction::Decode,
+            numeric: EntityAction::Decode,
+        };
+        let input = "&rlm;&lt;Caf&#233; &#x263A;&gt; &copy;";
+        assert_eq!(apply(input, &policy), "\u{200f}<Café ☺> &copy;");
+    }
+
+    #[test]
+    fn apply_preserve_invalid_numeric() {
+        let policy = EntityPolicy {
+            numeric: EntityAction::Decode,
+            ..Default::default()
+        };
+        assert_eq!(apply("&#xD800;", &policy), "&#xD800;");
+    }
+}
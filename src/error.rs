@@ -0,0 +1,287 @@
+//! errors produced while parsing cues, timecodes and tracks
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why parsing failed along with where, see [`ErrorKind`] and [`Span`]
+///
+/// ```
+/// use title_parser::{CueTrait, ErrorKind};
+///
+/// let text = "1\n00:01:14.815 --> 00:01:18.114";
+/// let err = text.to_cue().unwrap_err();
+/// assert_eq!(err.kind, ErrorKind::EmptyCueText);
+/// assert_eq!((err.span.line, err.span.column), (2, 30));
+/// assert_eq!(err.to_string(), "line 2, column 30: empty cue text");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// what went wrong
+    pub kind: ErrorKind,
+    /// where in the input it went wrong
+    pub span: Span,
+}
+
+impl ParseError {
+    /// Creates an error of the given kind found at `span`
+    pub fn new(kind: ErrorKind, span: Span) -> ParseError {
+        ParseError { kind, span }
+    }
+}
+
+// errors about a whole input, such as a timecode string, are located at
+// its start
+impl From<ErrorKind> for ParseError {
+    fn from(kind: ErrorKind) -> ParseError {
+        ParseError::new(kind, Span::default())
+    }
+}
+
+/// A position in the input handed to the parser
+///
+/// Positions within a document point at the document as passed in, byte
+/// order mark and `\r` line endings included, so editors can move the
+/// cursor straight to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// byte offset from the start of the input
+    pub offset: usize,
+    /// (1-based) line number
+    pub line: usize,
+    /// (1-based) column, counted in characters
+    pub column: usize,
+}
+
+impl Default for Span {
+    fn default() -> Span {
+        Span {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Span {
+    /// Finds the line and column of byte `offset` in `input`
+    ///
+    /// An offset past the end of `input` is clamped to its length, and
+    /// one inside a multi-byte character is moved back to the start of
+    /// that character, so this never panics.
+    ///
+    /// ```
+    /// use title_parser::Span;
+    ///
+    /// let span = Span::locate("WEBVTT\n\nぼく -->", 11);
+    /// assert_eq!((span.line, span.column), (3, 2));
+    /// ```
+    pub fn locate(input: &str, offset: usize) -> Span {
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Span {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    // Treats this span as relative to text starting at `origin`, giving
+    // its position in the enclosing input
+    pub(crate) fn within(self, origin: Span) -> Span {
+        Span {
+            offset: origin.offset + self.offset,
+            line: origin.line + self.line - 1,
+            column: if self.line == 1 {
+                origin.column + self.column - 1
+            } else {
+                self.column
+            },
+        }
+    }
+}
+
+/// Reasons a cue, timecode or track could not be parsed
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// a timecode is malformed or out of range
+    InvalidTimecode,
+    /// a time base has a rate of zero
+    InvalidTimeBase,
+    /// the timing line uses something other than `-->` as its arrow
+    MissingArrow,
+    /// no timing line could be found
+    InvalidCue,
+    /// the cue has no text and `ParseOptions::allow_empty` is off
+    EmptyCueText,
+    /// strict parsing found text before the cue identifier
+    TextBeforeTiming,
+    /// strict parsing found a cue ending before it starts
+    EndBeforeStart,
+    /// strict parsing found a cue that isn't separated from the previous
+    /// one by a blank line
+    MissingBlankLine,
+    /// the document was parsed as WebVTT but doesn't start with `WEBVTT`
+    MissingSignature,
+    /// the input is larger than `Limits::max_input_bytes`
+    InputTooLarge(usize),
+    /// the cue text is larger than `Limits::max_text_bytes`
+    TextTooLarge(usize),
+    /// the cue has more lines than `Limits::max_lines`
+    TooManyLines(usize),
+    /// the track has more cues than `Limits::max_cues`
+    TooManyCues(usize),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.span.line, self.span.column, self.kind
+        )
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidTimecode => write!(f, "invalid timecode"),
+            ErrorKind::InvalidTimeBase => write!(f, "invalid time base"),
+            ErrorKind::MissingArrow => write!(f, "malformed timing arrow, expected -->"),
+            ErrorKind::InvalidCue => write!(f, "not a valid cue"),
+            ErrorKind::EmptyCueText => write!(f, "empty cue text"),
+            ErrorKind::TextBeforeTiming => write!(f, "unexpected text before timing line"),
+            ErrorKind::EndBeforeStart => write!(f, "cue ends before it starts"),
+            ErrorKind::MissingBlankLine => write!(f, "missing blank line before cue"),
+            ErrorKind::MissingSignature => write!(f, "missing WEBVTT signature"),
+            ErrorKind::InputTooLarge(max) => write!(f, "input exceeds {} bytes", max),
+            ErrorKind::TextTooLarge(max) => write!(f, "cue text exceeds {} bytes", max),
+            ErrorKind::TooManyLines(max) => write!(f, "cue exceeds {} lines", max),
+            ErrorKind::TooManyCues(max) => write!(f, "track exceeds {} cues", max),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why reading a track from a file, reader or bytes failed
+///
+/// Parse errors keep their [`Span`], so tools can still point at the
+/// bad input, and errors about a file carry its path.
+///
+/// ```
+/// use title_parser::{Error, ErrorKind};
+/// use title_parser::track::Track;
+///
+/// let err = Track::from_bytes(b"1\n00:00:01,000 --> 00:00:02,000\n").unwrap_err();
+/// let parse = err.parse_error().unwrap();
+/// assert_eq!((parse.kind.clone(), parse.span.line), (ErrorKind::EmptyCueText, 2));
+///
+/// let err = Track::from_path("src/missing.srt").unwrap_err();
+/// assert!(matches!(err, Error::File(_, ref inner) if matches!(**inner, Error::Io(_))));
+/// ```
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// reading the input failed
+    Io(io::Error),
+    /// the input isn't valid in its (detected or given) text encoding
+    Encoding(String),
+    /// the text couldn't be parsed
+    Parse(ParseError),
+    /// a time window ends before it starts
+    InvalidWindow,
+    /// one of the other errors, for the file at the path
+    File(PathBuf, Box<Error>),
+}
+
+impl Error {
+    /// The parse error, with where it happened, if parsing is what
+    /// failed
+    pub fn parse_error(&self) -> Option<&ParseError> {
+        match self {
+            Error::Parse(error) => Some(error),
+            Error::File(_, error) => error.parse_error(),
+            _ => None,
+        }
+    }
+
+    /// The file the error is about, if any
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::File(path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    // Wraps the error with the file it is about
+    pub(crate) fn in_file(self, path: &Path) -> Error {
+        Error::File(path.to_path_buf(), Box::new(self))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Encoding(reason) => write!(f, "{}", reason),
+            Error::Parse(error) => write!(f, "{}", error),
+            Error::InvalidWindow => write!(f, "window ends before it starts"),
+            Error::File(path, error) => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Parse(error) => Some(error),
+            Error::File(_, error) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Error {
+        Error::Parse(error)
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::Parse(kind.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate_clamps_past_end() {
+        let span = Span::locate("WEBVTT\nab", 100);
+        assert_eq!((span.offset, span.line, span.column), (9, 2, 3));
+    }
+
+    #[test]
+    fn locate_rounds_down_to_char_boundary() {
+        let span = Span::locate("ぼく", 1);
+        assert_eq!((span.offset, span.line, span.column), (0, 1, 1));
+        let span = Span::locate("ぼく", 4);
+        assert_eq!((span.offset, span.line, span.column), (3, 1, 2));
+    }
+}
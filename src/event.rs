@@ -0,0 +1,225 @@
+//! low-level events for tools that don't need `Cue`s at all
+//!
+//! [`Events`] walks a document once and reports what it finds as it
+//! goes: the header, every cue's timing and raw text lines, notes and
+//! other WebVTT blocks. Text lines are handed over exactly as written,
+//! markup included, so a tool can rewrite a document in a single pass
+//! or build a representation of its own without allocating cues.
+
+use crate::timecode::TimeCode;
+use crate::track::{
+    detect_format, is_vtt_extra_block, normalize_newlines, relocate, split_cues, strip_bom,
+    vtt_signature, Blocks, Format,
+};
+use crate::{is_timing_line, BorrowedCue, ErrorKind, ParseError, ParseOptions, Span};
+use std::collections::VecDeque;
+
+/// Something found while walking a document, see [`Events`]
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    /// the header of a WebVTT document, SRT documents have none
+    Header {
+        /// text after the `WEBVTT` signature, if any
+        description: Option<&'a str>,
+        /// lines of the header below the signature
+        metadata: Vec<&'a str>,
+    },
+    /// a cue begins; its text lines follow, then [`Event::CueEnd`]
+    CueStart {
+        /// the line before the timing line, if the cue has one
+        identifier: Option<&'a str>,
+        /// timestamp for cue to appear
+        start: TimeCode,
+        /// timestamp for cue to disappear
+        end: TimeCode,
+        /// where the cue block starts in the document
+        span: Span,
+    },
+    /// a line of cue text, as written
+    TextLine(&'a str),
+    /// the comment of a WebVTT `NOTE` block
+    Note(&'a str),
+    /// a WebVTT `STYLE` or `REGION` block, as written
+    Block(&'a str),
+    /// the last text line of the cue has been reported
+    CueEnd,
+}
+
+/// Iterates over the [`Event`]s of an SRT or WebVTT document
+///
+/// Cues are checked as strictly as [`crate::track::Cues`] checks them.
+/// After an error no more events are produced, but the events of cues
+/// before the failing one are all reported first.
+///
+/// ```
+/// use title_parser::event::{Event, Events};
+///
+/// let text = "WEBVTT\n\nNOTE hi\n\n1\n00:00:01.000 --> 00:00:02.000\n<i>Hello</i>\nthere\n";
+/// let events: Vec<Event> = Events::new(text).map(|event| event.unwrap()).collect();
+/// assert_eq!(events[0], Event::Header { description: None, metadata: vec![] });
+/// assert_eq!(events[1], Event::Note("hi"));
+/// assert!(matches!(events[2], Event::CueStart { identifier: Some("1"), .. }));
+/// assert_eq!(events[3..], [Event::TextLine("<i>Hello</i>"), Event::TextLine("there"), Event::CueEnd]);
+/// ```
+pub struct Events<'a> {
+    blocks: Blocks<'a>,
+    format: Format,
+    options: ParseOptions,
+    // where `blocks` starts in the input, past any byte order mark
+    origin: Span,
+    pending: VecDeque<Event<'a>>,
+    // reported once the events before it are all out
+    error: Option<ParseError>,
+    count: usize,
+    done: bool,
+}
+
+impl<'a> Events<'a> {
+    /// Creates an iterator over the events of a document
+    pub fn new(input: &'a str) -> Events<'a> {
+        Events::with_options(input, &ParseOptions::default())
+    }
+
+    /// Creates an iterator over the events of a document using the given
+    /// options
+    pub fn with_options(input: &'a str, options: &ParseOptions) -> Events<'a> {
+        let (origin, input) = strip_bom(Span::default(), input);
+        let mut blocks = Blocks::new(input);
+        let format = detect_format(input);
+        let mut pending = VecDeque::new();
+        if format == Format::WebVtt {
+            if let Some((_, header)) = blocks.next() {
+                pending.push_back(Event::Header {
+                    description: vtt_signature(header).flatten(),
+                    metadata: header.lines().skip(1).collect(),
+                });
+            }
+        }
+        Events {
+            blocks,
+            format,
+            options: options.clone(),
+            origin,
+            pending,
+            error: None,
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Format of the document being walked
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    // Queues the events of a block
+    fn push_block(&mut self, at: Span, block: &'a str) -> Result<(), ParseError> {
+        if self.format == Format::WebVtt && is_vtt_extra_block(block) {
+            self.pending.push_back(match block.strip_prefix("NOTE") {
+                Some(note) => Event::Note(note.trim()),
+                None => Event::Block(block),
+            });
+            return Ok(());
+        }
+        let at = at.within(self.origin);
+        for (offset, text) in split_cues(block, self.options.mode) {
+            let cue_at = Span::locate(block, offset).within(at);
+            self.count += 1;
+            let max_cues = self.options.limits.max_cues;
+            if let Some(max) = max_cues.filter(|max| self.count > *max) {
+                return Err(ParseError::new(ErrorKind::TooManyCues(max), cue_at));
+            }
+            let normalized = normalize_newlines(text);
+            let cue = BorrowedCue::parse_with(&normalized, &self.options)
+                .map_err(|e| relocate(e, text, cue_at))?;
+            let lines: Vec<&str> = text.lines().map(|l| l.trim_end_matches('\r')).collect();
+            let timing = lines.iter().position(|line| is_timing_line(line));
+            let timing = timing.unwrap_or(0);
+            self.pending.push_back(Event::CueStart {
+                identifier: timing.checked_sub(1).map(|i| lines[i].trim()),
+                start: cue.start,
+                end: cue.end,
+                span: cue_at,
+            });
+            let text_lines = lines[timing + 1..].iter().map(|line| Event::TextLine(line));
+            self.pending.extend(text_lines);
+            self.pending.push_back(Event::CueEnd);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return self.error.take().map(Err);
+            }
+            match self.blocks.next() {
+                Some((at, block)) => {
+                    if let Err(e) = self.push_block(at, block) {
+                        self.error = Some(e);
+                        self.done = true;
+                    }
+                }
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::Track;
+    use crate::write::stamp;
+
+    #[test]
+    fn events_before_an_error_are_reported() {
+        let text = "1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\n\r\n2\r\n00:00:03,000 --> 00:00\r\nWorld\r\n";
+        let events: Vec<Result<Event, ParseError>> = Events::new(text).collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1], Ok(Event::TextLine("Hello")));
+        let err = events[3].as_ref().unwrap_err();
+        assert_eq!(
+            (err.kind.clone(), err.span.line),
+            (ErrorKind::InvalidTimecode, 6)
+        );
+    }
+
+    #[test]
+    fn single_pass_rewrite() -> Result<(), ParseError> {
+        let text = include_str!("example.vtt");
+        let mut out = String::new();
+        for event in Events::new(text) {
+            match event? {
+                Event::Header { .. } => out.push_str("WEBVTT\n"),
+                Event::CueStart { start, end, .. } => {
+                    let (start, end) = (start.total_millis(), end.total_millis());
+                    out.push_str(&format!(
+                        "\n{} --> {}\n",
+                        stamp(start, '.'),
+                        stamp(end, '.')
+                    ));
+                }
+                Event::TextLine(line) => out.push_str(&format!("{}\n", line.to_uppercase())),
+                _ => {}
+            }
+        }
+        let original = Track::parse(text)?;
+        let rewritten = Track::parse(&out)?;
+        assert_eq!(rewritten.cues.len(), original.cues.len());
+        for (a, b) in original.cues.iter().zip(&rewritten.cues) {
+            assert_eq!(
+                (a.start.total_millis(), a.text.to_uppercase()),
+                (b.start.total_millis(), b.text.clone())
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! checks what survives writing a track out and reading it back
+//!
+//! Useful when adding formats or options: run [`check`] over a corpus
+//! and every difference a round trip introduces is listed, rather than
+//! having to spot them in the output by hand.
+
+use crate::diff::{text_diff, Change};
+use crate::track::{Format, Track};
+use crate::{ParseError, ParseOptions};
+
+/// A way the round tripped track differs from the original
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// the written document could not be parsed again
+    Unparseable(ParseError),
+    /// the number of cues changed
+    CueCount {
+        /// cues in the original track
+        before: usize,
+        /// cues after the round trip
+        after: usize,
+    },
+    /// a cue moved, by the given number of milliseconds
+    Timing {
+        /// index of the cue
+        cue: usize,
+        /// change of the start time
+        start_delta_ms: i64,
+        /// change of the end time
+        end_delta_ms: i64,
+    },
+    /// words of a cue changed
+    Text {
+        /// index of the cue
+        cue: usize,
+        /// the word level edits, see [`text_diff`]
+        changes: Vec<Change>,
+    },
+    /// a cue kept its words but not its line breaks
+    LineBreaks {
+        /// index of the cue
+        cue: usize,
+    },
+    /// the notes of a cue changed
+    Notes {
+        /// index of the cue
+        cue: usize,
+    },
+    /// the WebVTT description or header metadata changed
+    Header,
+    /// styles were lost or changed
+    Styles,
+}
+
+/// Findings of a [`check`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// every difference found, in cue order
+    pub differences: Vec<Difference>,
+}
+
+impl Report {
+    /// Whether the round trip kept everything
+    pub fn is_lossless(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Parses `input`, writes it as `format`, parses the result and reports
+/// how it differs from the first parse
+///
+/// Cue text is compared as it is written in both documents, before
+/// sanitizing, so markup and dialogue dashes the parse strips, and so
+/// never reach the writer, are reported as lost. Fails only if `input`
+/// itself can't be parsed.
+///
+/// ```
+/// use title_parser::fidelity::{check, Difference};
+/// use title_parser::track::Format;
+///
+/// let text = "WEBVTT\n\nNOTE check this\n\n00:00:01.000 --> 00:00:02.000\nHello\n";
+/// assert!(check(text, Format::WebVtt).unwrap().is_lossless());
+/// let report = check(text, Format::Srt).unwrap();
+/// assert_eq!(report.differences, vec![Difference::Notes { cue: 0 }]);
+///
+/// let text = "1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i>\n";
+/// assert!(!check(text, Format::Srt).unwrap().is_lossless());
+/// ```
+pub fn check(input: &str, format: Format) -> Result<Report, ParseError> {
+    check_with(input, format, &ParseOptions::default())
+}
+
+/// Same as [`check`], parsing `input` with the given options before
+/// writing it
+///
+/// With `ParseOptions::raw_text` on, cue text is written as it was read.
+pub fn check_with(
+    input: &str,
+    format: Format,
+    options: &ParseOptions,
+) -> Result<Report, ParseError> {
+    let written = Track::parse_with(input, options)?.write(format);
+    // both sides are read raw, so text the parse changed shows up
+    let raw = ParseOptions {
+        raw_text: true,
+        ..options.clone()
+    };
+    let before = Track::parse_with(input, &raw)?;
+    let after = match Track::parse_with(&written, &raw) {
+        Ok(track) => track,
+        Err(e) => {
+            return Ok(Report {
+                differences: vec![Difference::Unparseable(e)],
+            })
+        }
+    };
+    Ok(compare(&before, &after, format))
+}
+
+fn compare(before: &Track, after: &Track, format: Format) -> Report {
+    let mut differences = Vec::new();
+    // SRT has no header to keep
+    let header = (&before.description, &before.metadata);
+    if format == Format::WebVtt && header != (&after.description, &after.metadata) {
+        differences.push(Difference::Header);
+    }
+    if before.styles != after.styles {
+        differences.push(Difference::Styles);
+    }
+    if before.cues.len() != after.cues.len() {
+        differences.push(Difference::CueCount {
+            before: before.cues.len(),
+            after: after.cues.len(),
+        });
+    }
+    for (cue, (a, b)) in before.cues.iter().zip(&after.cues).enumerate() {
+        let delta = |from: u64, to: u64| to as i64 - from as i64;
+        let start_delta_ms = delta(a.start.total_millis(), b.start.total_millis());
+        let end_delta_ms = delta(a.end.total_millis(), b.end.total_millis());
+        if start_delta_ms != 0 || end_delta_ms != 0 {
+            differences.push(Difference::Timing {
+                cue,
+                start_delta_ms,
+                end_delta_ms,
+            });
+        }
+        let changes = text_diff(&a.text, &b.text);
+        if !changes.is_empty() {
+            differences.push(Difference::Text { cue, changes });
+        } else if a.text != b.text {
+            differences.push(Difference::LineBreaks { cue });
+        }
+        if a.notes != b.notes {
+            differences.push(Difference::Notes { cue });
+        }
+    }
+    Report { differences }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_files_round_trip() -> Result<(), ParseError> {
+        let raw = ParseOptions {
+            raw_text: true,
+            ..Default::default()
+        };
+        for input in [include_str!("example.vtt"), include_str!("jp.vtt")] {
+            assert_eq!(check_with(input, Format::WebVtt, &raw)?, Report::default());
+        }
+        assert!(check(include_str!("jp.srt"), Format::Srt)?.is_lossless());
+        Ok(())
+    }
+
+    #[test]
+    fn srt_loses_styles_and_markup() -> Result<(), ParseError> {
+        let input = "WEBVTT\n\nSTYLE\n::cue { color: yellow }\n\n00:00:01.000 --> 00:00:02.000\n- a <b>bold</b> move\n";
+        let report = check(input, Format::Srt)?;
+        assert_eq!(report.differences[0], Difference::Styles);
+        let changes = match &report.differences[1..] {
+            [Difference::Text { cue: 0, changes }] => changes,
+            other => panic!("unexpected differences {:?}", other),
+        };
+        let removed: Vec<&str> = changes
+            .iter()
+            .flat_map(|change| change.old.iter().map(String::as_str))
+            .collect();
+        assert!(removed.contains(&"<b>bold</b>"));
+        let raw = ParseOptions {
+            raw_text: true,
+            ..Default::default()
+        };
+        assert!(check_with(input, Format::WebVtt, &raw)?.is_lossless());
+        Ok(())
+    }
+}
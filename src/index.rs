@@ -0,0 +1,458 @@
+//! keeps a compact on-disk index of a subtitle library
+//!
+//! Parsing thousands of files every time a tool starts is slow, so an
+//! [`Index`] records what search and browse tools need about each SRT
+//! and WebVTT file under a directory and saves it in a small binary
+//! file. Refreshing only parses files whose size or modification time
+//! changed since the last refresh.
+
+use crate::probe::path_language;
+use crate::track::{Format, Track};
+use crate::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// Start of every index file, followed by the format version
+const MAGIC: &[u8; 4] = b"TPIX";
+const VERSION: u8 = 1;
+
+/// What the index knows about one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// path of the file relative to the indexed directory, with `/`
+    /// separators
+    pub path: String,
+    /// size of the file in bytes when it was indexed
+    pub size: u64,
+    /// modification time of the file when it was indexed, in
+    /// nanoseconds since the Unix epoch
+    pub modified_ns: u64,
+    /// format of the file
+    pub format: Format,
+    /// language from a WebVTT `Language:` header, or from a file name
+    /// such as `movie.en.srt`
+    pub language: Option<String>,
+    /// number of cues
+    pub cue_count: u64,
+    /// see [`Track::content_hash`]
+    pub content_hash: u64,
+    /// earliest cue start, in milliseconds
+    pub first_start_ms: Option<u64>,
+    /// latest cue end, in milliseconds, which is also how long the
+    /// subtitles run
+    pub last_end_ms: Option<u64>,
+}
+
+/// Index of the subtitle files under a directory, see the module
+/// documentation
+///
+/// ```no_run
+/// use title_parser::index::Index;
+///
+/// let mut index = Index::load("library.idx").unwrap_or_default();
+/// let refresh = index.refresh("library").unwrap();
+/// println!("{} files parsed, {} unchanged", refresh.parsed, refresh.unchanged);
+/// index.save("library.idx").unwrap();
+/// for entry in index.entries.iter().filter(|e| e.language.as_deref() == Some("fr")) {
+///     println!("{} has {} cues", entry.path, entry.cue_count);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    /// every indexed file, ordered by path
+    pub entries: Vec<Entry>,
+}
+
+/// What a [`Index::refresh`] changed
+#[derive(Debug, Default)]
+pub struct Refresh {
+    /// files parsed because they are new or changed
+    pub parsed: usize,
+    /// files kept from the previous index
+    pub unchanged: usize,
+    /// entries dropped because their file is gone
+    pub removed: usize,
+    /// files that couldn't be read or parsed, with the error, which are
+    /// left out of the index
+    pub failed: Vec<(String, Error)>,
+}
+
+impl Index {
+    /// Indexes every SRT and WebVTT file under `root`
+    pub fn build<P: AsRef<Path>>(root: P) -> Result<(Index, Refresh), Error> {
+        let mut index = Index::default();
+        let refresh = index.refresh(root)?;
+        Ok((index, refresh))
+    }
+
+    /// Brings the index up to date with the files under `root`, parsing
+    /// only files that are new or whose size or modification time
+    /// changed
+    ///
+    /// Fails only if `root` can't be read, problems with single files
+    /// and directories are reported in the returned [`Refresh`]. Entries
+    /// under a directory that can't be read are kept as they were.
+    pub fn refresh<P: AsRef<Path>>(&mut self, root: P) -> Result<Refresh, Error> {
+        let root = root.as_ref();
+        let mut refresh = Refresh::default();
+        let mut files = Vec::new();
+        walk(root, root, &mut files, &mut refresh.failed)
+            .map_err(|e| Error::from(e).in_file(root))?;
+        let mut old: BTreeMap<String, Entry> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|entry| (entry.path.clone(), entry))
+            .collect();
+        // nothing is known about files under unreadable directories
+        let unreadable: Vec<String> = refresh
+            .failed
+            .iter()
+            .map(|(path, _)| format!("{}/", path))
+            .collect();
+        old.retain(|path, entry| {
+            let keep = unreadable.iter().any(|dir| path.starts_with(dir.as_str()));
+            if keep {
+                refresh.unchanged += 1;
+                self.entries.push(entry.clone());
+            }
+            !keep
+        });
+        for (path, size, modified_ns) in files {
+            match old.remove(&path) {
+                Some(entry) if entry.size == size && entry.modified_ns == modified_ns => {
+                    refresh.unchanged += 1;
+                    self.entries.push(entry);
+                }
+                _ => match index_file(&root.join(&path), path.clone(), size, modified_ns) {
+                    Ok(entry) => {
+                        refresh.parsed += 1;
+                        self.entries.push(entry);
+                    }
+                    Err(e) => refresh.failed.push((path, e)),
+                },
+            }
+        }
+        refresh.removed = old.len();
+        self.entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(refresh)
+    }
+
+    /// Looks up the entry of a file by its path relative to the indexed
+    /// directory
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        let found = self
+            .entries
+            .binary_search_by(|entry| entry.path.as_str().cmp(path));
+        found.ok().map(|i| &self.entries[i])
+    }
+
+    /// Encodes the index in its binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        put_u64(&mut out, self.entries.len() as u64);
+        for entry in &self.entries {
+            put_str(&mut out, &entry.path);
+            put_u64(&mut out, entry.size);
+            put_u64(&mut out, entry.modified_ns);
+            out.push(match entry.format {
+                Format::Srt => 0,
+                Format::WebVtt => 1,
+            });
+            match &entry.language {
+                Some(language) => {
+                    out.push(1);
+                    put_str(&mut out, language);
+                }
+                None => out.push(0),
+            }
+            put_u64(&mut out, entry.cue_count);
+            put_u64(&mut out, entry.content_hash);
+            for time in [entry.first_start_ms, entry.last_end_ms] {
+                out.push(u8::from(time.is_some()));
+                put_u64(&mut out, time.unwrap_or(0));
+            }
+        }
+        out
+    }
+
+    /// Decodes an index from its binary form
+    ///
+    /// Malformed input is an [`Error::Io`] of kind `InvalidData`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Index, Error> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not a subtitle index").into());
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(invalid(format!("unsupported index version {}", version)).into());
+        }
+        let count = reader.u64()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let path = reader.string()?;
+            let (size, modified_ns) = (reader.u64()?, reader.u64()?);
+            let format = match reader.u8()? {
+                0 => Format::Srt,
+                1 => Format::WebVtt,
+                other => return Err(invalid(format!("unknown format {} in index", other)).into()),
+            };
+            let language = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.string()?),
+            };
+            let (cue_count, content_hash) = (reader.u64()?, reader.u64()?);
+            let mut time = || -> io::Result<Option<u64>> {
+                let set = reader.u8()? != 0;
+                let ms = reader.u64()?;
+                Ok(set.then_some(ms))
+            };
+            let (first_start_ms, last_end_ms) = (time()?, time()?);
+            entries.push(Entry {
+                path,
+                size,
+                modified_ns,
+                format,
+                language,
+                cue_count,
+                content_hash,
+                first_start_ms,
+                last_end_ms,
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid("trailing bytes after index").into());
+        }
+        Ok(Index { entries })
+    }
+
+    /// Reads an index saved with [`Index::save`]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Index, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| Error::from(e).in_file(path))?;
+        Index::from_bytes(&bytes).map_err(|e| e.in_file(path))
+    }
+
+    /// Writes the index to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()).map_err(|e| Error::from(e).in_file(path))
+    }
+}
+
+// Collects the relative path, size and modification time of every SRT
+// and WebVTT file under `dir`. Files and directories below it that
+// can't be read are added to `failed` and skipped, only failing to read
+// `dir` itself is an error. Symbolic links are not followed, so linked
+// directories can't loop.
+fn walk(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, u64, u64)>,
+    failed: &mut Vec<(String, Error)>,
+) -> io::Result<()> {
+    let mut children: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|child| child.map(|child| child.path()))
+        .collect::<Result<_, _>>()?;
+    children.sort();
+    for path in children {
+        let Some(relative) = path.strip_prefix(root).ok().and_then(Path::to_str) else {
+            continue;
+        };
+        let relative = relative.replace(std::path::MAIN_SEPARATOR, "/");
+        let mut fail =
+            |e: io::Error| failed.push((relative.clone(), Error::from(e).in_file(&path)));
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                fail(e);
+                continue;
+            }
+        };
+        if metadata.is_dir() {
+            if let Err(e) = walk(root, &path, files, failed) {
+                failed.push((relative, Error::from(e).in_file(&path)));
+            }
+            continue;
+        }
+        let is_subtitle = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("srt") || ext.eq_ignore_ascii_case("vtt"));
+        if metadata.is_file() && is_subtitle {
+            match metadata.modified() {
+                Ok(modified) => {
+                    let modified = modified.duration_since(UNIX_EPOCH);
+                    let modified_ns = modified.map_or(0, |d| d.as_nanos() as u64);
+                    files.push((relative, metadata.len(), modified_ns));
+                }
+                Err(e) => fail(e),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn index_file(file: &Path, path: String, size: u64, modified_ns: u64) -> Result<Entry, Error> {
+    let track = Track::from_path(file)?;
+    let starts = track.cues.iter().map(|cue| cue.start.total_millis());
+    let ends = track.cues.iter().map(|cue| cue.end.total_millis());
+    Ok(Entry {
+        language: track.language().or_else(|| path_language(file)),
+        path,
+        size,
+        modified_ns,
+        format: track.format,
+        cue_count: track.cues.len() as u64,
+        content_hash: track.content_hash(),
+        first_start_ms: starts.min(),
+        last_end_ms: ends.max(),
+    })
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_u64(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn invalid<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// Reads the fields of an index, failing on truncated input
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, count: usize) -> io::Result<&'b [u8]> {
+        if count > self.bytes.len() {
+            return Err(invalid("index is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = usize::try_from(self.u64()?).map_err(invalid)?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory for one test, unique across test threads and
+    // test runs in parallel
+    fn scratch(test: &str) -> PathBuf {
+        let name = format!("title_parser_{}_{}", test, std::process::id());
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn refresh_parses_only_changed_files() -> Result<(), Error> {
+        let root = scratch("refresh_parses_only_changed_files");
+        fs::create_dir_all(root.join("season 1")).unwrap();
+        fs::write(root.join("a.vtt"), include_str!("example.vtt")).unwrap();
+        fs::write(root.join("season 1/b.fr.srt"), include_str!("jp.srt")).unwrap();
+        fs::write(root.join("notes.txt"), "not subtitles").unwrap();
+        fs::write(root.join("bad.srt"), "nonsense").unwrap();
+
+        let (mut index, refresh) = Index::build(&root)?;
+        assert_eq!((refresh.parsed, refresh.failed.len()), (2, 1));
+        let entry = index.get("season 1/b.fr.srt").unwrap();
+        assert_eq!(
+            (entry.format, entry.language.as_deref()),
+            (Format::Srt, Some("fr"))
+        );
+        assert_eq!(index.get("a.vtt").unwrap().last_end_ms, Some(83_868));
+
+        fs::write(
+            root.join("a.vtt"),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n",
+        )
+        .unwrap();
+        fs::remove_file(root.join("season 1/b.fr.srt")).unwrap();
+        let refresh = index.refresh(&root)?;
+        assert_eq!(
+            (refresh.parsed, refresh.unchanged, refresh.removed),
+            (1, 0, 1)
+        );
+        assert_eq!(index.get("a.vtt").unwrap().cue_count, 1);
+        assert_eq!(index.refresh(&root)?.unchanged, 1);
+        fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directories_do_not_stop_refresh() -> Result<(), Error> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = scratch("unreadable_directories_do_not_stop_refresh");
+        fs::create_dir_all(root.join("locked")).unwrap();
+        fs::write(root.join("a.vtt"), include_str!("example.vtt")).unwrap();
+        fs::write(root.join("locked/b.srt"), include_str!("jp.srt")).unwrap();
+        let (mut index, _) = Index::build(&root)?;
+        let locked = root.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // permissions don't stop privileged users, such as root in CI
+        if fs::read_dir(&locked).is_err() {
+            fs::write(root.join("c.srt"), include_str!("jp.srt")).unwrap();
+            let refresh = index.refresh(&root)?;
+            assert_eq!(refresh.failed.len(), 1);
+            assert_eq!(refresh.failed[0].1.path(), Some(locked.as_path()));
+            assert_eq!((refresh.parsed, refresh.unchanged), (1, 2));
+            assert!(index.get("locked/b.srt").is_some());
+        }
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn binary_form_round_trips() -> Result<(), Error> {
+        let index = Index {
+            entries: vec![Entry {
+                path: "films/Ното.vtt".to_string(),
+                size: 1234,
+                modified_ns: 1_700_000_000_000_000_000,
+                format: Format::WebVtt,
+                language: Some("ru".to_string()),
+                cue_count: 42,
+                content_hash: 0xDEAD_BEEF,
+                first_start_ms: Some(0),
+                last_end_ms: None,
+            }],
+        };
+        let bytes = index.to_bytes();
+        assert_eq!(Index::from_bytes(&bytes)?, index);
+        assert!(Index::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Index::from_bytes(b"TPIX\x02").is_err());
+        Ok(())
+    }
+}
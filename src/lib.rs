@@ -4,9 +4,52 @@
 //!
 //! Provides a parser that will extract a sequence of Cues
 //! from text that conforms to SRT or WebVTT standards
+//!
+//! Single cue blocks are parsed with [`CueTrait`], whole documents
+//! with [`track::Track`].
+
+// Emits a `tracing` event at the given level with the `tracing`
+// feature, and compiles to nothing without it
+macro_rules! trace {
+    ($level:ident, $($event:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($event)*);
+    };
+}
 
+pub mod burnlist;
+pub mod cea608;
+pub mod clip;
+pub mod describe;
+pub mod diff;
+mod encoding;
+pub mod entity;
+mod error;
+pub mod event;
+pub mod fidelity;
+pub mod index;
+pub mod lint;
+#[cfg(feature = "memmap2")]
+pub mod mmap;
+pub mod parallel;
+pub mod parser;
+pub mod probe;
+pub mod regroup;
+pub mod schedule;
+pub mod shared;
+pub mod split;
+pub mod stream;
+pub mod style;
 pub mod timecode;
-use regex::{Captures, Regex};
+pub mod track;
+pub mod warning;
+pub mod watermark;
+pub mod write;
+use entity::EntityPolicy;
+pub use error::{Error, ErrorKind, ParseError, Span};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
 use timecode::{TimeCode, TimeCodeTrait};
 // use std::{error, fs};
 
@@ -20,6 +63,7 @@ use timecode::{TimeCode, TimeCodeTrait};
 /// - This line is also a member of the same cue.
 /// ```
 ///
+#[derive(Debug)]
 pub struct Cue {
     /// timestamp for cue to appear
     pub start: TimeCode,
@@ -27,6 +71,212 @@ pub struct Cue {
     pub end: TimeCode,
     /// text for cue to display
     pub text: String,
+    /// editorial comments about the cue, taken from the WebVTT `NOTE`
+    /// blocks right before it
+    pub notes: Vec<String>,
+}
+
+impl Cue {
+    /// Produces a shortened copy of the cue text for small displays
+    ///
+    /// Keeps at most `max_lines` lines of at most `max_chars` characters
+    /// each. When anything is cut, the last kept line ends with `…`
+    /// (counted towards `max_chars`). The cue itself is left untouched.
+    /// Characters are counted as in [`Cue::chars_per_second`].
+    ///
+    /// ```
+    /// use title_parser::{CueTrait};
+    ///
+    /// let text = "00:01:14.815 --> 00:01:18.114\n- What?\n- Where are we now?";
+    /// let cue = text.to_cue().unwrap();
+    /// assert_eq!(cue.truncate_display(10, 2), "What?\nWhere are…");
+    /// assert_eq!(cue.truncate_display(10, 1), "What?…");
+    /// assert_eq!(cue.text, "What?\nWhere are we now?");
+    /// ```
+    pub fn truncate_display(&self, max_chars: usize, max_lines: usize) -> String {
+        if max_chars == 0 || max_lines == 0 {
+            return String::new();
+        }
+        let lines: Vec<&str> = self.text.lines().collect();
+        let mut truncated = lines.len() > max_lines;
+        let mut kept: Vec<String> = Vec::new();
+        for line in lines.iter().take(max_lines) {
+            if text_len(line) > max_chars {
+                truncated = true;
+                kept.push(take_text(line, max_chars).to_string());
+                break;
+            }
+            kept.push(line.to_string());
+        }
+        if truncated {
+            if let Some(last) = kept.last_mut() {
+                let room = max_chars - 1;
+                let mut shortened = take_text(last, room).to_string();
+                shortened.truncate(shortened.trim_end().len());
+                shortened.push('…');
+                *last = shortened;
+            }
+        }
+        kept.join("\n")
+    }
+
+    /// Reading speed of the cue in characters per second
+    ///
+    /// Runs of whitespace, line breaks included, count as a single
+    /// character. Cues without a duration have infinite speed. With the
+    /// `unicode-segmentation` feature characters are grapheme clusters,
+    /// so emoji sequences and combining marks count once.
+    ///
+    /// ```
+    /// use title_parser::CueTrait;
+    ///
+    /// let cue = "00:00:01.000 --> 00:00:03.000\nHello\nthere".to_cue().unwrap();
+    /// assert_eq!(cue.chars_per_second(), 5.5);
+    /// ```
+    pub fn chars_per_second(&self) -> f64 {
+        let duration = self
+            .end
+            .total_millis()
+            .saturating_sub(self.start.total_millis());
+        text_chars(&self.text) as f64 * 1000.0 / duration as f64
+    }
+}
+
+/// Options controlling how cue text is parsed
+///
+/// ```
+/// use title_parser::{CueTrait, ParseOptions};
+///
+/// let text = "00:01:14.815 --> 00:01:18.114\n- I'm text for a cue";
+/// let cue = text.to_cue_with(&ParseOptions::default()).unwrap();
+/// assert_eq!(cue.text, "I'm text for a cue");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// how HTML entities in cue text are treated
+    pub entities: EntityPolicy,
+    /// accept cues without any text, such as clear markers or timing
+    /// placeholders, instead of rejecting them
+    ///
+    /// ```
+    /// use title_parser::{CueTrait, ErrorKind, ParseOptions};
+    ///
+    /// let text = "00:01:14.815 --> 00:01:18.114";
+    /// assert_eq!(text.to_cue().unwrap_err().kind, ErrorKind::EmptyCueText);
+    ///
+    /// let options = ParseOptions { allow_empty: true, ..Default::default() };
+    /// let cue = text.to_cue_with(&options).unwrap();
+    /// assert_eq!(cue.text, "");
+    /// ```
+    pub allow_empty: bool,
+    /// keep cue text exactly as written, skipping the removal of markup,
+    /// dialogue dashes and entities, for callers that process the text
+    /// themselves
+    ///
+    /// No pass is made over the text at all, so this is the fastest way
+    /// to parse tag-heavy files. `entities` has no effect.
+    ///
+    /// ```
+    /// use title_parser::{CueTrait, ParseOptions};
+    ///
+    /// let text = "00:01:14.815 --> 00:01:18.114\n- <i>Tom</i> &amp; Jerry";
+    /// let options = ParseOptions { raw_text: true, ..Default::default() };
+    /// assert_eq!(text.to_cue_with(&options).unwrap().text, "- <i>Tom</i> &amp; Jerry");
+    /// ```
+    pub raw_text: bool,
+    /// upper bounds on input accepted by the parser
+    pub limits: Limits,
+    /// how closely input has to follow the SRT / WebVTT specs
+    pub mode: ParseMode,
+    /// how the text encoding of raw bytes is chosen, see
+    /// [`track::Track::from_bytes_with`]
+    pub encoding: Encoding,
+}
+
+/// How the text encoding of raw input is chosen
+///
+/// Short files give detection little to go on, so a file in a
+/// Central European code page can easily be mistaken for windows-1252.
+/// Hints and overrides give callers who know better a way to say so.
+/// Labels are WHATWG encoding labels such as `windows-1250`; anything
+/// other than UTF-8 and UTF-16 needs the `encoding_rs` feature.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    /// detect the encoding from byte order marks and the content
+    #[default]
+    Detect,
+    /// detect the encoding, but use the labelled one instead of guessing
+    /// when the input is neither UTF-8 nor UTF-16
+    Hint(String),
+    /// always decode with the labelled encoding
+    Override(String),
+}
+
+/// How strictly the parser follows the SRT / WebVTT specs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// only accept input that conforms to the specs, rejecting text
+    /// before the cue identifier, cues that end before they start and
+    /// cues missing the blank line that separates them
+    ///
+    /// This is the default since 0.2.0, which breaks 0.1 callers relying
+    /// on `to_cue` skipping text before the identifier. They can switch
+    /// to [`ParseMode::Lenient`].
+    ///
+    /// ```
+    /// use title_parser::{CueTrait, ErrorKind};
+    ///
+    /// let text = "00:00:01,000 --> 00:00:02,000\nHello\n00:00:03,000 --> 00:00:04,000\nWorld";
+    /// assert_eq!(text.to_cue().unwrap_err().kind, ErrorKind::MissingBlankLine);
+    /// ```
+    #[default]
+    Strict,
+    /// also accept malformed input that players commonly tolerate:
+    /// `->` or `—>` instead of `-->` on the timing line, stray
+    /// whitespace around lines, one or two digit milliseconds (read as
+    /// a fraction of a second) and, in whole documents, cues that are
+    /// not separated by blank lines
+    ///
+    /// ```
+    /// use title_parser::{CueTrait, ErrorKind, ParseMode, ParseOptions};
+    ///
+    /// let text = "00:01:14.815\t->  00:01:18.114\n- I'm text for a cue";
+    /// assert_eq!(text.to_cue().unwrap_err().kind, ErrorKind::MissingArrow);
+    ///
+    /// let options = ParseOptions { mode: ParseMode::Lenient, ..Default::default() };
+    /// let cue = text.to_cue_with(&options).unwrap();
+    /// assert_eq!(cue.start.to_seconds(), 74);
+    /// ```
+    Lenient,
+}
+
+/// Resource limits applied while parsing, `None` means unlimited
+///
+/// Useful when parsing untrusted input, as oversized input is rejected
+/// before any pattern matching happens.
+///
+/// ```
+/// use title_parser::{CueTrait, ErrorKind, Limits, ParseOptions};
+///
+/// let options = ParseOptions {
+///     limits: Limits { max_lines: Some(1), ..Default::default() },
+///     ..Default::default()
+/// };
+/// let text = "00:01:14.815 --> 00:01:18.114\n- I'm text for a cue\n- Me too!";
+/// let err = text.to_cue_with(&options).unwrap_err();
+/// assert_eq!(err.kind, ErrorKind::TooManyLines(1));
+/// assert_eq!(err.span.line, 3);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// maximum size in bytes of the input handed to the parser
+    pub max_input_bytes: Option<usize>,
+    /// maximum size in bytes of a cue's text before sanitization
+    pub max_text_bytes: Option<usize>,
+    /// maximum number of text lines in a cue
+    pub max_lines: Option<usize>,
+    /// maximum number of cues in a track
+    pub max_cues: Option<usize>,
 }
 
 /// trait to implement for types that can be converted to
@@ -54,47 +304,320 @@ pub trait CueTrait {
     /// let cue = text.to_cue().unwrap();
     /// assert_eq!(cue.text, "I'm text for a cue\nMe too!");
     /// ```
-    fn to_cue(&self) -> Result<Cue, String>;
+    fn to_cue(&self) -> Result<Cue, ParseError> {
+        self.to_cue_with(&ParseOptions::default())
+    }
+
+    /// Attempts to create a cue from a string using the given options
+    fn to_cue_with(&self, options: &ParseOptions) -> Result<Cue, ParseError>;
 }
 
 impl CueTrait for str {
-    fn to_cue(&self) -> Result<Cue, String> {
-        let re = Regex::new(r"(.+\n)?(([0-9:\.,]{9,}) --> ([0-9:\.,]{9,})( .*)?)((\n.*)+)")
-            .expect("failed to compile regex");
-        let caps = re
-            .captures(self)
-            .ok_or_else(|| "not a valid cue".to_string())?;
-        let cues = caps.get(6).unwrap().as_str();
-        let (start, end) = generate_timecodes(caps).ok_or_else(|| "not a valid cue".to_string())?;
-        let lines: Vec<&str> = cues.trim().split('\n').into_iter().collect();
-        let clean_lines: Vec<String> = lines.iter().map(|i| sanitize_text(i)).collect();
-        let text = clean_lines.join("\n");
-        Ok(Cue { start, end, text })
+    fn to_cue_with(&self, options: &ParseOptions) -> Result<Cue, ParseError> {
+        BorrowedCue::parse_with(self, options).map(BorrowedCue::into_owned)
     }
 }
 
-// Attempts to extract TimeCodes from input, ignores css formatting text
-fn generate_timecodes(caps: Captures) -> Option<(TimeCode, TimeCode)> {
-    let start = caps.get(3)?.as_str().to_timecode().ok()?;
-    let end = caps.get(4)?.as_str().to_timecode().ok()?;
-    Some((start, end))
+/// A cue whose text borrows from the input it was parsed from
+///
+/// Parsing a [`Cue`] always allocates its text. A `BorrowedCue` only
+/// does when the text differs from the input, such as when tags are
+/// stripped, lines are joined after lenient tidying, or entities are
+/// decoded. Timecodes are still owned.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use title_parser::BorrowedCue;
+///
+/// let input = "1\n00:01:14.815 --> 00:01:18.114\nplain text\nover two lines";
+/// let cue = BorrowedCue::parse(input).unwrap();
+/// assert!(matches!(cue.text, Cow::Borrowed("plain text\nover two lines")));
+///
+/// let cue = BorrowedCue::parse("00:01:14.815 --> 00:01:18.114\n- <i>styled</i>").unwrap();
+/// assert!(matches!(cue.text, Cow::Owned(_)));
+/// assert_eq!(cue.into_owned().text, "styled");
+/// ```
+#[derive(Debug)]
+pub struct BorrowedCue<'a> {
+    /// timestamp for cue to appear
+    pub start: TimeCode,
+    /// timestamp for cue to disappear
+    pub end: TimeCode,
+    /// text for cue to display
+    pub text: Cow<'a, str>,
+}
+
+impl<'a> BorrowedCue<'a> {
+    /// Attempts to parse a cue, see [`CueTrait::to_cue`]
+    pub fn parse(source: &'a str) -> Result<BorrowedCue<'a>, ParseError> {
+        BorrowedCue::parse_with(source, &ParseOptions::default())
+    }
+
+    /// Attempts to parse a cue using the given options, see
+    /// [`CueTrait::to_cue_with`]
+    pub fn parse_with(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<BorrowedCue<'a>, ParseError> {
+        let limits = &options.limits;
+        if exceeds(source.len(), limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(limits.max_input_bytes.unwrap()).into());
+        }
+        let lenient = options.mode == ParseMode::Lenient;
+        let input = if lenient {
+            Cow::Owned(tidy_lenient(source))
+        } else {
+            Cow::Borrowed(source)
+        };
+        // lenient parsing works on tidied up input, whose lines still
+        // match those of the original
+        let fail = |kind: ErrorKind, offset: usize| {
+            let span = Span::locate(&input, offset);
+            let span = if lenient {
+                line_start(source, span.line)
+            } else {
+                span
+            };
+            trace!(debug, kind = %kind, line = span.line, "cue rejected");
+            ParseError::new(kind, span)
+        };
+        let timing = find_timing(&input).ok_or_else(|| match LOOSE_ARROW.find(source) {
+            Some(timing) => {
+                trace!(
+                    debug,
+                    arrow = timing.as_str(),
+                    "timing line arrow malformed"
+                );
+                ParseError::new(
+                    ErrorKind::MissingArrow,
+                    Span::locate(source, timing.start()),
+                )
+            }
+            None => {
+                trace!(debug, "no timing line found");
+                ErrorKind::InvalidCue.into()
+            }
+        })?;
+        // only a cue identifier may come before the timing line
+        let identifier = timing
+            .line
+            .checked_sub(1)
+            .and_then(|line| line_offsets(&input).nth(line))
+            .map_or(0, |(offset, _)| offset);
+        if !lenient && (timing.prefixed || !input[..identifier].trim().is_empty()) {
+            let text = input.len() - input.trim_start().len();
+            return Err(fail(ErrorKind::TextBeforeTiming, text));
+        }
+        let parse = |(offset, timecode): (usize, &str)| {
+            match options.mode {
+                ParseMode::Strict => timecode.to_timecode(),
+                ParseMode::Lenient => timecode::parse_lenient(timecode),
+            }
+            .map_err(|e| fail(e.kind, offset))
+        };
+        let (start, end) = (parse(timing.start)?, parse(timing.end)?);
+        trace!(
+            trace,
+            start_ms = start.total_millis(),
+            end_ms = end.total_millis(),
+            "timing line parsed"
+        );
+        let cues = &input[timing.line_end..];
+        if !lenient {
+            if end.total_millis() < start.total_millis() {
+                return Err(fail(ErrorKind::EndBeforeStart, timing.start.0));
+            }
+            if let Some(line) = line_offsets(cues).find(|(_, l)| is_timing_line(l)) {
+                return Err(fail(ErrorKind::MissingBlankLine, timing.line_end + line.0));
+            }
+        }
+        let text = cues.trim();
+        let text_start = timing.line_end + cues.len() - cues.trim_start().len();
+        if exceeds(text.len(), limits.max_text_bytes) {
+            let kind = ErrorKind::TextTooLarge(limits.max_text_bytes.unwrap());
+            return Err(fail(kind, text_start));
+        }
+        if let Some(max) = limits.max_lines {
+            if let Some((offset, _)) = line_offsets(text).nth(max) {
+                return Err(fail(ErrorKind::TooManyLines(max), text_start + offset));
+            }
+        }
+        // strict parsing works on the input itself, so unchanged text
+        // can point straight into it
+        let written = || match lenient {
+            false => Cow::Borrowed(&source[text_start..text_start + text.len()]),
+            true => Cow::Owned(text.to_string()),
+        };
+        let text = if options.raw_text {
+            written()
+        } else {
+            let clean_lines: Vec<Cow<str>> = line_offsets(text)
+                .map(|(_, line)| sanitize_text(line, &options.entities))
+                .collect();
+            match clean_lines
+                .iter()
+                .all(|line| matches!(line, Cow::Borrowed(_)))
+            {
+                true => written(),
+                false => Cow::Owned(clean_lines.join("\n")),
+            }
+        };
+        if text.is_empty() && !options.allow_empty {
+            return Err(fail(ErrorKind::EmptyCueText, timing.line_end));
+        }
+        trace!(
+            trace,
+            borrowed = matches!(text, Cow::Borrowed(_)),
+            "cue parsed"
+        );
+        Ok(BorrowedCue { start, end, text })
+    }
+
+    /// Copies the text out of the input, giving a [`Cue`]
+    pub fn into_owned(self) -> Cue {
+        Cue {
+            start: self.start,
+            end: self.end,
+            text: self.text.into_owned(),
+            notes: Vec::new(),
+        }
+    }
+}
+
+// The timing line of a cue, with byte offsets into the cue block
+struct Timing<'t> {
+    // (0-based) index of the line
+    line: usize,
+    // where the start and end timestamps are, and their text
+    start: (usize, &'t str),
+    end: (usize, &'t str),
+    // where the line ends, past any cue settings
+    line_end: usize,
+    // whether other text comes before the start timestamp on the line
+    prefixed: bool,
+}
+
+const BLANK: [char; 3] = [' ', '\t', '\r'];
+
+// Finds the timing line following the WebVTT cue block rules: the first
+// line holding a `-->` arrow with whitespace on both sides is the
+// timing line, with the start timestamp right before the arrow and the
+// end timestamp right after it
+fn find_timing(input: &str) -> Option<Timing<'_>> {
+    line_offsets(input)
+        .enumerate()
+        .find_map(|(index, (offset, line))| {
+            let arrow = line
+                .match_indices("-->")
+                .map(|(i, _)| i)
+                .find(|&i| line[..i].ends_with(BLANK) && line[i + 3..].starts_with(BLANK))?;
+            let (left, right) = (&line[..arrow], &line[arrow + 3..]);
+            let left = left.trim_end_matches(BLANK);
+            let start_at = left.rfind(BLANK).map_or(0, |i| i + 1);
+            let right = right.trim_start_matches(BLANK);
+            let end_at = line.len() - right.len();
+            let end_len = right.find(BLANK).unwrap_or(right.len());
+            Some(Timing {
+                line: index,
+                start: (offset + start_at, &left[start_at..]),
+                end: (offset + end_at, &right[..end_len]),
+                line_end: offset + line.len(),
+                prefixed: !left[..start_at].trim().is_empty(),
+            })
+        })
+}
+
+// Splits text on `\n`, yielding the byte offset of every line with it
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line))
+    })
 }
 
-static REGEX_TO_PRUNE: [&str; 3] = [r"<[0-9a-zA-Z\.,:_\-]+>", r"</[0-9a-zA-Z\.,:_\-]+>", r"^\- "];
+// Locates the first non-blank character of a (1-based) line
+fn line_start(input: &str, line: usize) -> Span {
+    let (offset, text) = line_offsets(input).nth(line - 1).unwrap_or((0, input));
+    Span::locate(input, offset + text.len() - text.trim_start().len())
+}
+
+// Counts characters for reading speed, whitespace runs counting once
+pub(crate) fn text_chars(text: &str) -> usize {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let letters: usize = words.iter().map(|word| text_len(word)).sum();
+    letters + words.len().saturating_sub(1)
+}
+
+// Length of text as seen on screen: grapheme clusters with the
+// `unicode-segmentation` feature, `char`s without
+pub(crate) fn text_len(text: &str) -> usize {
+    #[cfg(feature = "unicode-segmentation")]
+    return unicode_segmentation::UnicodeSegmentation::graphemes(text, true).count();
+    #[cfg(not(feature = "unicode-segmentation"))]
+    return text.chars().count();
+}
+
+// The first `count` characters of text, as counted by `text_len`
+pub(crate) fn take_text(text: &str, count: usize) -> &str {
+    #[cfg(feature = "unicode-segmentation")]
+    let mut ends = unicode_segmentation::UnicodeSegmentation::grapheme_indices(text, true);
+    #[cfg(not(feature = "unicode-segmentation"))]
+    let mut ends = text.char_indices();
+    ends.nth(count).map_or(text, |(at, _)| &text[..at])
+}
+
+// Checks a measured size against an optional limit
+pub(crate) fn exceeds(size: usize, limit: Option<usize>) -> bool {
+    limit.is_some_and(|max| size > max)
+}
 
-static ES_TO_PRUNE: [&str; 6] = ["&amp;", "&lt;", "&gt;", "&lrm;", "&rlm;", "&nbsp;"];
+// Matches timing lines, including ones with a malformed arrow
+static LOOSE_ARROW: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"([0-9:\.,]{6,})[ \t]*[-—–]+[ \t]*>[ \t]*([0-9:\.,]{6,})")
+        .expect("failed to compile regex")
+});
 
-// Removes leading hyphens, HTML tags, CSS tags, etc. from input
-fn sanitize_text(input: &str) -> String {
-    let mut text: String = input.to_string();
-    for regex in REGEX_TO_PRUNE.iter() {
-        let re = Regex::new(regex).expect("unable to compile regex");
-        text = re.replace_all(&text, "").to_string();
+// Markup removed from every line of cue text
+static PRUNE: Lazy<[Regex; 3]> = Lazy::new(|| {
+    [r"<[0-9a-zA-Z\.,:_\-]+>", r"</[0-9a-zA-Z\.,:_\-]+>", r"^\- "]
+        .map(|pattern| Regex::new(pattern).expect("unable to compile regex"))
+});
+
+// Checks whether a line looks like a cue timing line
+pub(crate) fn is_timing_line(line: &str) -> bool {
+    LOOSE_ARROW.is_match(line)
+}
+
+// Trims stray whitespace from every line and fixes up the timing arrow
+fn tidy_lenient(input: &str) -> String {
+    let lines: Vec<&str> = input.lines().map(str::trim).collect();
+    LOOSE_ARROW
+        .replacen(&lines.join("\n"), 1, "$1 --> $2")
+        .into_owned()
+}
+
+// Removes leading hyphens, HTML tags, CSS tags, etc. from input and
+// applies the entity policy to what remains, borrowing the input when
+// nothing changes
+fn sanitize_text<'t>(input: &'t str, entities: &EntityPolicy) -> Cow<'t, str> {
+    let mut text = Cow::Borrowed(input);
+    for re in PRUNE.iter() {
+        if let Cow::Owned(pruned) = re.replace_all(&text, "") {
+            text = Cow::Owned(pruned);
+        }
+    }
+    if let Cow::Owned(applied) = entity::apply(&text, entities) {
+        text = Cow::Owned(applied);
     }
-    for es in ES_TO_PRUNE.iter() {
-        text = text.replace(es, "");
+    match text {
+        Cow::Owned(changed) if changed == input => Cow::Borrowed(input),
+        Cow::Owned(changed) => {
+            trace!(trace, from = input, to = changed.as_str(), "text sanitized");
+            Cow::Owned(changed)
+        }
+        text => text,
     }
-    text
 }
 
 #[cfg(test)]
@@ -102,9 +625,181 @@ mod tests {
     use super::*;
 
     #[test]
-    fn private_sanitize_text() -> Result<(), String> {
+    fn private_sanitize_text() -> Result<(), ParseError> {
         let input = "<c.japanese><c.bg_some>&lrm;（聖弥）フフッ</c.bg_some></c.japanese>";
-        assert_eq!(sanitize_text(input), "（聖弥）フフッ".to_string());
+        assert_eq!(
+            sanitize_text(input, &EntityPolicy::default()),
+            "（聖弥）フフッ".to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn empty_cue_with_trailing_newline() -> Result<(), ParseError> {
+        let input = "1\n00:01:14.815 --> 00:01:18.114\n";
+        assert_eq!(input.to_cue().unwrap_err().kind, ErrorKind::EmptyCueText);
+        let options = ParseOptions {
+            allow_empty: true,
+            ..Default::default()
+        };
+        let cue = input.to_cue_with(&options)?;
+        assert_eq!(cue.text, "");
+        assert_eq!(cue.end.to_seconds(), 78);
+        Ok(())
+    }
+
+    #[test]
+    fn borrowed_cue_allocates_only_for_changes() -> Result<(), ParseError> {
+        let input = "00:01:14.815 --> 00:01:18.114\nTom &copy; Jerry\n";
+        let cue = BorrowedCue::parse(input)?;
+        assert!(matches!(cue.text, Cow::Borrowed("Tom &copy; Jerry")));
+        let cue = BorrowedCue::parse("00:01:14.815 --> 00:01:18.114\n&lrm;Hi")?;
+        assert_eq!(cue.text, "Hi");
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let cue = BorrowedCue::parse_with("00:01:14.815 -> 00:01:18.114\nHi", &options)?;
+        assert!(matches!(cue.text, Cow::Owned(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn timing_line_follows_cue_block_rules() -> Result<(), ParseError> {
+        let cue = "intro\n00:01:14.815\t-->   00:01:18.114 align:start line:0\nHi".to_cue()?;
+        assert_eq!((cue.end.to_seconds(), cue.text.as_str()), (78, "Hi"));
+        // the arrow has to stand apart from the timestamps
+        let err = "00:01:14.815-->00:01:18.114\nHi".to_cue().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingArrow);
+        let err = "1\n00:01:14 --> 00:01:18.114\nHi".to_cue().unwrap_err();
+        assert_eq!((err.kind, err.span.line), (ErrorKind::InvalidTimecode, 2));
+        let err = "junk 00:01:14.815 --> 00:01:18.114\nHi"
+            .to_cue()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TextBeforeTiming);
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_display_edge_cases() -> Result<(), ParseError> {
+        let cue = "00:01:14.815 --> 00:01:18.114\nabc\ndef".to_cue()?;
+        assert_eq!(cue.truncate_display(3, 2), "abc\ndef");
+        assert_eq!(cue.truncate_display(1, 1), "…");
+        assert_eq!(cue.truncate_display(0, 2), "");
+        Ok(())
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn graphemes_count_once() -> Result<(), ParseError> {
+        // a family emoji joined by ZWJs, and an e with a combining accent
+        let cue = "00:00:01.000 --> 00:00:02.000
+\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} cafe\u{301}!"
+            .to_cue()?;
+        assert_eq!(cue.chars_per_second(), 7.0);
+        assert_eq!(
+            cue.truncate_display(4, 1),
+            "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} c…"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_arrow_spellings() -> Result<(), ParseError> {
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        for arrow in ["->", "—>", "–>", "-- >", "--->", "  -->  "] {
+            let input = format!("1\n00:01:14.815{}00:01:18.114\nI'm text", arrow);
+            let cue = input.to_cue_with(&options)?;
+            assert_eq!(cue.end.to_seconds(), 78);
+            assert_eq!(cue.text, "I'm text");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn strict_rejects_nonconforming_cues() {
+        let cases = [
+            (
+                "\njunk\n1\n00:00:01.000 --> 00:00:02.000\ntext",
+                ErrorKind::TextBeforeTiming,
+                (1, 2, 1),
+            ),
+            (
+                "1\n00:00:02.000 --> 00:00:01.000\ntext",
+                ErrorKind::EndBeforeStart,
+                (2, 2, 1),
+            ),
+            (
+                "00:00:01.000 --> 00:00:02.00\ntext",
+                ErrorKind::InvalidTimecode,
+                (17, 1, 18),
+            ),
+            (
+                "00:00:01.000 --> 00:00:02.000\ntext\n00:00:03.000 --> 00:00:04.000",
+                ErrorKind::MissingBlankLine,
+                (35, 3, 1),
+            ),
+        ];
+        for (input, kind, (offset, line, column)) in cases {
+            let err = input.to_cue().unwrap_err();
+            assert_eq!(err.kind, kind);
+            assert_eq!(
+                err.span,
+                Span {
+                    offset,
+                    line,
+                    column
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn lenient_accepts_sloppy_cues() -> Result<(), ParseError> {
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let input = "  1 \n 00:00:01,5 --> 00:01:2,25  \n  I'm text  \n";
+        let cue = input.to_cue_with(&options)?;
+        assert_eq!(cue.start.total_millis(), 1_500);
+        assert_eq!(cue.end.total_millis(), 62_250);
+        assert_eq!(cue.text, "I'm text");
+        let err = "  1\n  00:00:01,5 --> 00:61:00,0\ntext"
+            .to_cue_with(&options)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidTimecode);
+        assert_eq!((err.span.offset, err.span.line, err.span.column), (6, 2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn limits_reject_oversized_input() -> Result<(), ParseError> {
+        let input = "00:01:14.815 --> 00:01:18.114\nI'm text for a cue";
+        let options = ParseOptions {
+            limits: Limits {
+                max_input_bytes: Some(16),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            input.to_cue_with(&options).err(),
+            Some(ErrorKind::InputTooLarge(16).into())
+        );
+        let options = ParseOptions {
+            limits: Limits {
+                max_text_bytes: Some(4),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = input.to_cue_with(&options).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TextTooLarge(4));
+        assert_eq!(err.span.line, 2);
         Ok(())
     }
 }
@@ -0,0 +1,220 @@
+//! checks cue text against the typography rules of its language
+//!
+//! Caption QC criteria differ by language, so rules come in packs
+//! picked by the track's `Language:` header, or chosen by hand for
+//! tracks without one:
+//!
+//!   - French puts a (narrow) no-break space before `?`, `!` and `;`
+//!   - Spanish opens questions and exclamations with `¿` and `¡`
+//!   - Chinese and Japanese end lines with full-width punctuation
+
+use crate::probe::header_language;
+use crate::track::Track;
+use std::fmt;
+
+/// A set of rules for one language, see the module documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulePack {
+    /// rules for French
+    French,
+    /// rules for Spanish
+    Spanish,
+    /// rules for Chinese and Japanese
+    Cjk,
+}
+
+/// Kind of rule a cue breaks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// the mark isn't preceded by a no-break space
+    MissingNoBreakSpace(char),
+    /// the closing mark has no opening `¿` or `¡` before it
+    MissingOpening(char),
+    /// the opening mark is never closed
+    Unclosed(char),
+    /// the line ends with a half-width mark instead of a full-width one
+    HalfWidthPunctuation(char),
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::MissingNoBreakSpace(c) => write!(f, "no no-break space before {}", c),
+            LintKind::MissingOpening(c) => write!(f, "{} has no opening mark", c),
+            LintKind::Unclosed(c) => write!(f, "{} is never closed", c),
+            LintKind::HalfWidthPunctuation(c) => write!(f, "half-width {} at line end", c),
+        }
+    }
+}
+
+/// A rule broken by a cue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// index of the cue
+    pub cue: usize,
+    /// (1-based) line of the cue text
+    pub line: usize,
+    /// what is wrong
+    pub kind: LintKind,
+}
+
+impl RulePack {
+    /// Picks the pack for a BCP 47 language tag such as `fr-CA`
+    ///
+    /// ```
+    /// use title_parser::lint::RulePack;
+    ///
+    /// assert_eq!(RulePack::for_language("fr-CA"), Some(RulePack::French));
+    /// assert_eq!(RulePack::for_language("zh-Hant"), Some(RulePack::Cjk));
+    /// assert_eq!(RulePack::for_language("en"), None);
+    /// ```
+    pub fn for_language(tag: &str) -> Option<RulePack> {
+        let primary = tag.split(['-', '_']).next().unwrap_or("");
+        match primary.to_ascii_lowercase().as_str() {
+            "fr" => Some(RulePack::French),
+            "es" => Some(RulePack::Spanish),
+            "zh" | "ja" => Some(RulePack::Cjk),
+            _ => None,
+        }
+    }
+
+    /// Checks the text of one cue, returning every broken rule with the
+    /// (1-based) line it is on
+    pub fn check(&self, text: &str) -> Vec<(usize, LintKind)> {
+        match self {
+            RulePack::French => french(text),
+            RulePack::Spanish => spanish(text),
+            RulePack::Cjk => cjk(text),
+        }
+    }
+}
+
+fn french(text: &str) -> Vec<(usize, LintKind)> {
+    let mut found = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let mut previous = None;
+        for c in line.chars() {
+            // `?!` only needs the space before the first mark
+            let spaced = matches!(previous, Some('\u{a0}' | '\u{202f}' | '?' | '!' | ';'));
+            if matches!(c, '?' | '!' | ';') && previous.is_some() && !spaced {
+                found.push((index + 1, LintKind::MissingNoBreakSpace(c)));
+            }
+            previous = Some(c);
+        }
+    }
+    found
+}
+
+fn spanish(text: &str) -> Vec<(usize, LintKind)> {
+    let mut found = Vec::new();
+    // open marks along with the line they are on, innermost last
+    let mut open: Vec<(char, usize)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        for c in line.chars() {
+            let opening = match c {
+                '¿' | '¡' => {
+                    open.push((c, index + 1));
+                    continue;
+                }
+                '?' => '¿',
+                '!' => '¡',
+                _ => continue,
+            };
+            match open.iter().rposition(|&(mark, _)| mark == opening) {
+                Some(at) => {
+                    open.remove(at);
+                }
+                None => found.push((index + 1, LintKind::MissingOpening(c))),
+            }
+        }
+    }
+    found.extend(
+        open.into_iter()
+            .map(|(c, line)| (line, LintKind::Unclosed(c))),
+    );
+    found.sort_by_key(|&(line, _)| line);
+    found
+}
+
+fn cjk(text: &str) -> Vec<(usize, LintKind)> {
+    let is_cjk =
+        |c: char| ('\u{3040}'..='\u{30ff}').contains(&c) || ('\u{4e00}'..='\u{9fff}').contains(&c);
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.chars().any(is_cjk))
+        .filter_map(|(index, line)| {
+            let last = line.trim_end().chars().last()?;
+            matches!(last, '.' | ',' | '?' | '!' | ':' | ';')
+                .then(|| (index + 1, LintKind::HalfWidthPunctuation(last)))
+        })
+        .collect()
+}
+
+impl Track {
+    /// Language of the track from its WebVTT `Language:` header
+    pub fn language(&self) -> Option<String> {
+        header_language(&self.metadata.join("\n"))
+    }
+
+    /// Checks every cue against the rules for the track's language, see
+    /// [`Track::language`]
+    ///
+    /// Tracks in languages without a pack, or without a language, have
+    /// nothing to check.
+    ///
+    /// ```
+    /// use title_parser::lint::{Lint, LintKind};
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "WEBVTT\nLanguage: fr\n\n00:00:01.000 --> 00:00:02.000\nVraiment?\n";
+    /// let track = Track::parse(text).unwrap();
+    /// assert_eq!(track.lint(), vec![Lint { cue: 0, line: 1, kind: LintKind::MissingNoBreakSpace('?') }]);
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let pack = self.language().as_deref().and_then(RulePack::for_language);
+        pack.map_or_else(Vec::new, |pack| self.lint_with(pack))
+    }
+
+    /// Checks every cue against the given rules
+    pub fn lint_with(&self, pack: RulePack) -> Vec<Lint> {
+        self.cues
+            .iter()
+            .enumerate()
+            .flat_map(|(cue, c)| {
+                pack.check(&c.text)
+                    .into_iter()
+                    .map(move |(line, kind)| Lint { cue, line, kind })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spanish_marks_pair_across_lines() {
+        let pack = RulePack::Spanish;
+        assert!(pack.check("¿Vienes\nmañana? ¡Sí!").is_empty());
+        assert_eq!(
+            pack.check("Vienes?\n¡Claro"),
+            vec![
+                (1, LintKind::MissingOpening('?')),
+                (2, LintKind::Unclosed('¡'))
+            ]
+        );
+    }
+
+    #[test]
+    fn french_and_cjk_rules() {
+        let french = RulePack::French;
+        assert!(french.check("Quoi\u{202f}?!\nAh\u{a0}; bon").is_empty());
+        assert_eq!(french.check("Quoi ?").len(), 1);
+        let cjk = RulePack::Cjk;
+        assert_eq!(
+            cjk.check("本当?\nOK.\nはい。"),
+            vec![(1, LintKind::HalfWidthPunctuation('?'))]
+        );
+    }
+}
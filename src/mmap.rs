@@ -0,0 +1,140 @@
+//! parses memory-mapped files without reading them into memory first
+//!
+//! The operating system pages the file in as the parser reaches it, and
+//! [`BorrowedCue`](crate::BorrowedCue)s point straight into the mapping,
+//! so even archives of hundreds of megabytes are never copied as a
+//! whole. Files that aren't UTF-8 have to be decoded, which does copy
+//! them.
+
+use crate::encoding::decode;
+use crate::track::{BorrowedCues, Confidence, Cues, Format, Track};
+use crate::{Encoding, Error, ParseError, ParseOptions};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::path::Path;
+
+enum Source {
+    // a mapping holding valid UTF-8 from the given offset on
+    Mapped(Mmap, usize),
+    Decoded(String),
+}
+
+/// A memory-mapped SRT or WebVTT file, see [`Track::from_mmap`]
+pub struct MappedTrack {
+    source: Source,
+    encoding: &'static str,
+    confidence: Confidence,
+}
+
+impl Track {
+    /// Memory-maps an SRT or WebVTT file for parsing with borrowed cues
+    ///
+    /// As with any memory-mapped file, the file must not be changed
+    /// while the returned `MappedTrack` is alive. Errors are
+    /// [`Error::File`]s carrying the path.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let mapped = Track::from_mmap("src/example.vtt").unwrap();
+    /// let texts: Vec<String> = mapped.cues().map(|cue| cue.unwrap().text.into_owned()).collect();
+    /// assert_eq!(texts.len(), 3);
+    /// assert_eq!(mapped.to_track().unwrap().cues.len(), 3);
+    /// ```
+    pub fn from_mmap<P: AsRef<Path>>(path: P) -> Result<MappedTrack, Error> {
+        let path = path.as_ref();
+        let context = |e: Error| e.in_file(path);
+        let file = File::open(path).map_err(|e| context(e.into()))?;
+        // Safety: the caller promises not to change the file while it is
+        // mapped, as documented above
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| context(e.into()))?;
+        let decoded = decode(&map, &Encoding::Detect).map_err(|e| context(Error::Encoding(e)))?;
+        let (encoding, confidence) = (decoded.encoding, decoded.confidence);
+        // UTF-8 is borrowed from the mapping, past any byte order mark
+        let (offset, decoded) = match decoded.text {
+            Cow::Borrowed(text) => (map.len() - text.len(), None),
+            Cow::Owned(text) => (0, Some(text)),
+        };
+        let source = match decoded {
+            Some(text) => Source::Decoded(text),
+            None => Source::Mapped(map, offset),
+        };
+        Ok(MappedTrack {
+            source,
+            encoding,
+            confidence,
+        })
+    }
+}
+
+impl MappedTrack {
+    /// Text of the file, after any byte order mark
+    pub fn text(&self) -> &str {
+        match &self.source {
+            // Safety: decoding checked it is UTF-8 when mapped, and the
+            // mapping can't change while borrowed
+            Source::Mapped(map, offset) => unsafe {
+                std::str::from_utf8_unchecked(&map[*offset..])
+            },
+            Source::Decoded(text) => text,
+        }
+    }
+
+    /// Name of the encoding the file is stored in
+    pub fn encoding(&self) -> &'static str {
+        self.encoding
+    }
+
+    /// Format of the file, see [`crate::track::detect_format`]
+    pub fn format(&self) -> Format {
+        crate::track::detect_format(self.text())
+    }
+
+    /// Iterates over the cues of the file, borrowing their text from the
+    /// mapping where possible
+    pub fn cues(&self) -> BorrowedCues<'_> {
+        Cues::new(self.text()).borrowed()
+    }
+
+    /// Iterates over the cues of the file using the given options
+    pub fn cues_with(&self, options: &ParseOptions) -> BorrowedCues<'_> {
+        Cues::with_options(self.text(), options).borrowed()
+    }
+
+    /// Parses the whole file into an owned track
+    pub fn to_track(&self) -> Result<Track, ParseError> {
+        let mut track = Track::parse(self.text())?;
+        track.encoding = Some(self.encoding);
+        track.encoding_confidence = Some(self.confidence);
+        Ok(track)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_cues_borrow_from_the_file() -> Result<(), ParseError> {
+        let name = format!(
+            "title_parser_mapped_cues_borrow_from_the_file_{}",
+            std::process::id()
+        );
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bom.srt");
+        std::fs::write(
+            &path,
+            b"\xEF\xBB\xBF1\n00:00:01,000 --> 00:00:02,000\nHello\n",
+        )
+        .unwrap();
+        let mapped = Track::from_mmap(&path).unwrap();
+        assert_eq!(mapped.format(), Format::Srt);
+        let cue = mapped.cues().next().unwrap()?;
+        assert!(matches!(cue.text, Cow::Borrowed("Hello")));
+        assert!(Track::from_mmap(dir.join("missing.srt")).is_err());
+        Ok(())
+    }
+}
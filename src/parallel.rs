@@ -0,0 +1,236 @@
+//! parses long documents on several threads
+//!
+//! The document is cut into chunks of whole blocks, so no cue is ever
+//! split between two threads. Worker threads parse the cues of each
+//! chunk and send them back over a channel, and the calling thread adds
+//! them to the track in document order, exactly as a single threaded
+//! parse would. Errors are the same as [`Track::parse_with`]'s, and the
+//! first one in the document is reported.
+//!
+//! With the `rayon` feature, [`Track::par_parse`] does the same on the
+//! rayon thread pool, for programs already using it.
+
+use crate::track::{
+    is_vtt_extra_block, parse_cues, read_path, Blocks, ParsedCues, Track, TrackBuilder,
+};
+use crate::{exceeds, Error, ErrorKind, ParseError, ParseOptions, Span};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+// Chunks handed out per thread, so threads finishing early pick up work
+// left over by slower ones
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Reads and parses an SRT or WebVTT file on `threads` worker threads,
+/// or as many as the machine has cores when `threads` is 0
+///
+/// Behaves like [`Track::from_path`] otherwise.
+///
+/// ```
+/// use title_parser::parallel::parse_file_parallel;
+///
+/// let track = parse_file_parallel("src/example.vtt", 2).unwrap();
+/// assert_eq!(track.cues.len(), 3);
+/// ```
+pub fn parse_file_parallel<P: AsRef<Path>>(path: P, threads: usize) -> Result<Track, Error> {
+    let options = ParseOptions::default();
+    read_path(path.as_ref(), &options, |input| {
+        Track::parse_parallel(input, threads, &options)
+    })
+}
+
+impl Track {
+    /// Attempts to parse an entire SRT or WebVTT document on `threads`
+    /// worker threads, or as many as the machine has cores when
+    /// `threads` is 0, see the module documentation
+    ///
+    /// ```
+    /// use title_parser::ParseOptions;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+    /// let track = Track::parse_parallel(text, 4, &ParseOptions::default()).unwrap();
+    /// assert_eq!(track.cues[1].text, "World");
+    /// ```
+    pub fn parse_parallel(
+        input: &str,
+        threads: usize,
+        options: &ParseOptions,
+    ) -> Result<Track, ParseError> {
+        if exceeds(input.len(), options.limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(options.limits.max_input_bytes.unwrap()).into());
+        }
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let mut builder = TrackBuilder::new(options);
+        let mut blocks = Blocks::new(input);
+        if let Some((at, block)) = blocks.next() {
+            builder.push_block(at, block)?;
+        }
+        let chunks = chunk(blocks.collect(), threads * CHUNKS_PER_THREAD);
+
+        let jobs = Mutex::new(chunks.iter().enumerate());
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let (sender, results) = mpsc::channel::<(usize, Vec<Option<ParsedCues>>)>();
+            for _ in 0..threads.min(chunks.len()) {
+                let sender = sender.clone();
+                let (jobs, stop) = (&jobs, &stop);
+                scope.spawn(move || loop {
+                    let job = jobs.lock().unwrap().next();
+                    let Some((index, chunk)) = job else { break };
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let parsed = chunk
+                        .iter()
+                        .map(|&(at, block)| {
+                            (!is_vtt_extra_block(block)).then(|| parse_cues(at, block, options))
+                        })
+                        .collect();
+                    if sender.send((index, parsed)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            // chunks that arrived before the ones preceding them
+            let mut waiting = BTreeMap::new();
+            let mut next = 0;
+            for (index, parsed) in results.iter() {
+                waiting.insert(index, parsed);
+                while let Some(parsed) = waiting.remove(&next) {
+                    for (&(at, block), cues) in chunks[next].iter().zip(parsed) {
+                        if let Err(e) = builder.push_parsed(at, block, cues) {
+                            stop.store(true, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                    }
+                    next += 1;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Track {
+    /// Attempts to parse an entire SRT or WebVTT document, parsing its
+    /// blocks in parallel on the rayon thread pool
+    ///
+    /// ```
+    /// use title_parser::ParseOptions;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+    /// let track = Track::par_parse(text, &ParseOptions::default()).unwrap();
+    /// assert_eq!(track.cues[1].text, "World");
+    /// ```
+    pub fn par_parse(input: &str, options: &ParseOptions) -> Result<Track, ParseError> {
+        use rayon::prelude::*;
+
+        if exceeds(input.len(), options.limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(options.limits.max_input_bytes.unwrap()).into());
+        }
+        let mut builder = TrackBuilder::new(options);
+        let mut blocks = Blocks::new(input);
+        if let Some((at, block)) = blocks.next() {
+            builder.push_block(at, block)?;
+        }
+        let blocks: Vec<(Span, &str)> = blocks.collect();
+        let parsed: Vec<Option<ParsedCues>> = blocks
+            .par_iter()
+            .map(|&(at, block)| {
+                (!is_vtt_extra_block(block)).then(|| parse_cues(at, block, options))
+            })
+            .collect();
+        for (&(at, block), cues) in blocks.iter().zip(parsed) {
+            builder.push_parsed(at, block, cues)?;
+        }
+        Ok(builder.finish())
+    }
+}
+
+// Cuts blocks into at most `count` runs of roughly the same size in bytes
+fn chunk(blocks: Vec<(Span, &str)>, count: usize) -> Vec<Vec<(Span, &str)>> {
+    let total: usize = blocks.iter().map(|(_, block)| block.len()).sum();
+    let size = total / count.max(1) + 1;
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut filled = 0;
+    for (at, block) in blocks {
+        filled += block.len();
+        current.push((at, block));
+        if filled >= size {
+            chunks.push(std::mem::take(&mut current));
+            filled = 0;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_vtt() -> String {
+        let example = include_str!("example.vtt");
+        let (header, cues) = example.split_once("\n\n").unwrap();
+        let mut text = format!("{}\n\n", header);
+        for _ in 0..200 {
+            text.push_str(cues);
+            text.push_str("\n\nNOTE between\n\n");
+        }
+        text
+    }
+
+    #[test]
+    fn parallel_matches_sequential() -> Result<(), ParseError> {
+        let text = long_vtt();
+        let options = ParseOptions::default();
+        let expected = Track::parse_with(&text, &options)?;
+        for threads in [1, 3, 8] {
+            let track = Track::parse_parallel(&text, threads, &options)?;
+            assert_eq!(track.cues.len(), expected.cues.len());
+            assert_eq!(track.content_hash(), expected.content_hash());
+            assert_eq!(track.cues[3].notes, expected.cues[3].notes);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_reports_the_first_error() {
+        let mut text = long_vtt();
+        text.push_str("00:00:01.000 -> 00:00:02.000\nlate\n");
+        let at = text.match_indices("\n\n").nth(5).unwrap().0;
+        text.insert_str(at, "\n\n00:00:01.000 --> 00:00\nearly");
+        let options = ParseOptions::default();
+        let expected = Track::parse_with(&text, &options).unwrap_err();
+        let err = Track::parse_parallel(&text, 4, &options).unwrap_err();
+        assert_eq!(err, expected);
+        assert_eq!(err.kind, ErrorKind::InvalidTimecode);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn rayon_matches_sequential() -> Result<(), ParseError> {
+        let text = long_vtt();
+        let options = ParseOptions::default();
+        let expected = Track::parse_with(&text, &options)?;
+        let track = Track::par_parse(&text, &options)?;
+        assert_eq!(track.content_hash(), expected.content_hash());
+        assert_eq!(track.cues[3].notes, expected.cues[3].notes);
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! a reusable parser carrying its options
+//!
+//! The patterns used for parsing are compiled once, the first time any
+//! parser needs them, and shared by every parse after that. A `Parser`
+//! keeps its options alongside, so code parsing many cues or documents
+//! the same way sets them up once.
+
+use crate::event::Events;
+use crate::track::{Cues, Track};
+use crate::{BorrowedCue, Cue, CueTrait, ParseError, ParseOptions};
+
+/// Parses cues and documents with a fixed set of options
+///
+/// ```
+/// use title_parser::parser::Parser;
+/// use title_parser::{ParseMode, ParseOptions};
+///
+/// let parser = Parser::new(ParseOptions { mode: ParseMode::Lenient, ..Default::default() });
+/// for text in ["00:00:01,000 -> 00:00:02,000\nHello", "00:00:03,000 —> 00:00:04,000\nWorld"] {
+///     assert!(parser.cue(text).is_ok());
+/// }
+/// let track = parser.track("1\n00:00:01,000 -> 00:00:02,000\nHello\n").unwrap();
+/// assert_eq!(track.cues[0].text, "Hello");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Parser {
+    options: ParseOptions,
+}
+
+impl Parser {
+    /// Creates a parser using the given options
+    pub fn new(options: ParseOptions) -> Parser {
+        Parser { options }
+    }
+
+    /// Options the parser was created with
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Attempts to parse a single cue, see [`CueTrait::to_cue_with`]
+    pub fn cue(&self, input: &str) -> Result<Cue, ParseError> {
+        input.to_cue_with(&self.options)
+    }
+
+    /// Attempts to parse a single cue borrowing from `input`, see
+    /// [`BorrowedCue::parse_with`]
+    pub fn borrowed_cue<'a>(&self, input: &'a str) -> Result<BorrowedCue<'a>, ParseError> {
+        BorrowedCue::parse_with(input, &self.options)
+    }
+
+    /// Attempts to parse an entire document, see [`Track::parse_with`]
+    pub fn track(&self, input: &str) -> Result<Track, ParseError> {
+        Track::parse_with(input, &self.options)
+    }
+
+    /// Iterates over the cues of a document, see [`Cues::with_options`]
+    pub fn cues<'a>(&self, input: &'a str) -> Cues<'a> {
+        Cues::with_options(input, &self.options)
+    }
+
+    /// Iterates over the events of a document, see
+    /// [`Events::with_options`]
+    pub fn events<'a>(&self, input: &'a str) -> Events<'a> {
+        Events::with_options(input, &self.options)
+    }
+}
@@ -0,0 +1,184 @@
+//! cheaply inspects subtitle documents without parsing every cue
+
+use crate::encoding::decode;
+use crate::timecode::{TimeCode, TimeCodeTrait};
+use crate::track::{detect_format, Format, Track};
+use crate::{Encoding, Error};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Bytes read from each end of a file by `Track::probe_file`
+const PROBE_WINDOW: u64 = 64 * 1024;
+
+/// Summary of a subtitle document gathered without a full parse
+#[derive(Debug, PartialEq)]
+pub struct Probe {
+    /// detected format of the document
+    pub format: Format,
+    /// language from a WebVTT `Language:` header, or from a file name
+    /// such as `movie.en.srt`
+    pub language: Option<String>,
+    /// estimated number of cues, based on the number of timing lines
+    pub cue_count_estimate: usize,
+    /// start of the first cue
+    pub first_start: Option<TimeCode>,
+    /// end of the last cue
+    pub last_end: Option<TimeCode>,
+}
+
+impl Track {
+    /// Quickly summarises a document by scanning for timing lines
+    /// instead of parsing each cue
+    ///
+    /// ```
+    /// use title_parser::track::{Format, Track};
+    ///
+    /// let text = "WEBVTT\nLanguage: en\n\n00:00:01.000 --> 00:00:02.000\nHi\n\n00:01:14.815 --> 00:01:18.114\nBye\n";
+    /// let probe = Track::probe(text);
+    /// assert_eq!(probe.format, Format::WebVtt);
+    /// assert_eq!(probe.language.as_deref(), Some("en"));
+    /// assert_eq!(probe.cue_count_estimate, 2);
+    /// assert_eq!(probe.last_end.unwrap().as_str(), "00:01:18.114");
+    /// ```
+    pub fn probe(input: &str) -> Probe {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        Probe {
+            format: detect_format(input),
+            language: header_language(input),
+            cue_count_estimate: input.matches("-->").count(),
+            first_start: input.find("-->").and_then(|i| timing_at(input, i).0),
+            last_end: input.rfind("-->").and_then(|i| timing_at(input, i).1),
+        }
+    }
+
+    /// Quickly summarises a file, reading only its first and last 64KB
+    ///
+    /// For larger files the cue count is extrapolated from the density
+    /// of timing lines in the first window.
+    pub fn probe_file<P: AsRef<Path>>(path: P) -> Result<Probe, Error> {
+        let path = path.as_ref();
+        let context = |e: std::io::Error| Error::from(e).in_file(path);
+        let mut file = File::open(path).map_err(context)?;
+        let size = file.metadata().map_err(context)?.len();
+
+        let mut probe = if size <= PROBE_WINDOW * 2 {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).map_err(context)?;
+            Track::probe(&text(&bytes))
+        } else {
+            let mut head = vec![0; PROBE_WINDOW as usize];
+            file.read_exact(&mut head).map_err(context)?;
+            let mut tail = vec![0; PROBE_WINDOW as usize];
+            file.seek(SeekFrom::End(-(PROBE_WINDOW as i64)))
+                .map_err(context)?;
+            file.read_exact(&mut tail).map_err(context)?;
+
+            let mut probe = Track::probe(&text(&head));
+            let tail = Track::probe(&text(&tail));
+            probe.last_end = tail.last_end;
+            let per_window = probe.cue_count_estimate as u64;
+            probe.cue_count_estimate = (per_window * size / PROBE_WINDOW) as usize;
+            probe
+        };
+        if probe.language.is_none() {
+            probe.language = path_language(path);
+        }
+        Ok(probe)
+    }
+}
+
+// Decodes a window of bytes, tolerating characters cut at the edges
+fn text(bytes: &[u8]) -> String {
+    match decode(bytes, &Encoding::Detect) {
+        Ok(decoded) => decoded.text.into_owned(),
+        Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+// Reads `Language:` from the WebVTT header block
+pub(crate) fn header_language(input: &str) -> Option<String> {
+    input
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("language"))
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// Takes the language from names like `movie.en.srt` or `movie.pt-BR.vtt`
+pub(crate) fn path_language(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, tag) = stem.rsplit_once('.')?;
+    let primary = tag.split('-').next()?;
+    let is_tag =
+        (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_alphabetic());
+    if is_tag {
+        Some(tag.to_string())
+    } else {
+        None
+    }
+}
+
+// Parses the timecodes of the timing line containing the arrow at `arrow`
+fn timing_at(input: &str, arrow: usize) -> (Option<TimeCode>, Option<TimeCode>) {
+    let line_start = input[..arrow].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = input[arrow..].find('\n').map_or(input.len(), |i| arrow + i);
+    let start = input[line_start..arrow].trim();
+    let end = input[arrow + 3..line_end]
+        .split_whitespace()
+        .next()
+        .unwrap_or("");
+    (start.to_timecode().ok(), end.to_timecode().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_srt() {
+        let probe = Track::probe(include_str!("jp.srt"));
+        assert_eq!(probe.format, Format::Srt);
+        assert_eq!(probe.language, None);
+        assert_eq!(probe.cue_count_estimate, 5);
+        assert_eq!(probe.first_start.unwrap().as_str(), "00:00:12,584");
+        assert_eq!(probe.last_end.unwrap().as_str(), "00:00:29,917");
+    }
+
+    #[test]
+    fn probe_large_file() -> Result<(), Error> {
+        let example = include_str!("example.vtt");
+        let (header, cues) = example.split_once("\n\n").unwrap();
+        let mut text = format!("{}\n\n", header);
+        for _ in 0..2_000 {
+            text.push_str(cues);
+            text.push_str("\n\n");
+        }
+        let name = format!("title_parser_probe_large_file_{}", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bats.en.vtt");
+        std::fs::write(&path, &text).unwrap();
+
+        let probe = Track::probe_file(&path)?;
+        assert_eq!(probe.format, Format::WebVtt);
+        assert_eq!(probe.language.as_deref(), Some("en"));
+        assert_eq!(probe.last_end.unwrap().as_str(), "00:01:23.868");
+        let estimate = probe.cue_count_estimate as f64;
+        assert!((estimate - 6_000.0).abs() < 300.0, "estimate {}", estimate);
+        Ok(())
+    }
+
+    #[test]
+    fn path_language_tags() {
+        assert_eq!(
+            path_language(Path::new("a.pt-BR.srt")).as_deref(),
+            Some("pt-BR")
+        );
+        assert_eq!(path_language(Path::new("a.srt")), None);
+        assert_eq!(path_language(Path::new("a.final.srt")), None);
+    }
+}
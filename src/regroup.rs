@@ -0,0 +1,264 @@
+//! re-partitions cue text to keep reading speed within limits
+//!
+//! Fixing one cue at a time only goes so far: a dense cue next to a
+//! sparse one is better fixed by moving words between them. The
+//! optimizer merges cues too short to read, stretches cues into the
+//! silence after them and moves words across cue boundaries, always
+//! keeping the minimum gap between cues.
+
+use crate::timecode::TimeCode;
+use crate::{text_chars, Cue, ParseError};
+
+/// Constraints the regrouped cues should meet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegroupOptions {
+    /// highest acceptable reading speed, in characters per second
+    pub max_cps: f64,
+    /// shortest acceptable cue duration, in milliseconds
+    pub min_duration_ms: u64,
+    /// shortest acceptable gap between consecutive cues, in milliseconds
+    pub min_gap_ms: u64,
+}
+
+impl Default for RegroupOptions {
+    // common broadcast guidelines: 17 cps, 5/6 of a second on screen
+    // and two frames (at 24fps) between cues
+    fn default() -> RegroupOptions {
+        RegroupOptions {
+            max_cps: 17.0,
+            min_duration_ms: 833,
+            min_gap_ms: 83,
+        }
+    }
+}
+
+// A cue being regrouped, keeping the original around so untouched cues
+// come out exactly as they went in
+struct Group {
+    cue: Cue,
+    start: u64,
+    end: u64,
+    words: Vec<String>,
+    edited: bool,
+}
+
+impl Group {
+    fn cps(&self) -> f64 {
+        cps(&self.words, self.end - self.start)
+    }
+}
+
+fn cps(words: &[String], duration: u64) -> f64 {
+    text_chars(&words.join(" ")) as f64 * 1000.0 / duration.max(1) as f64
+}
+
+/// Regroups consecutive cues to reduce reading speed violations
+///
+/// Cues are expected in display order. Cues whose text moved lose their
+/// line breaks, everything else about untouched cues is kept.
+///
+/// ```
+/// use title_parser::CueTrait;
+/// use title_parser::regroup::{regroup, RegroupOptions};
+///
+/// let cues = vec![
+///     "00:00:01.000 --> 00:00:02.000\nThis cue has far too much text to read".to_cue().unwrap(),
+///     "00:00:02.100 --> 00:00:05.000\nin time".to_cue().unwrap(),
+/// ];
+/// let options = RegroupOptions::default();
+/// let cues = regroup(cues, &options).unwrap();
+/// assert!(cues.iter().all(|cue| cue.chars_per_second() <= options.max_cps));
+/// ```
+pub fn regroup(cues: Vec<Cue>, options: &RegroupOptions) -> Result<Vec<Cue>, ParseError> {
+    let mut groups: Vec<Group> = cues
+        .into_iter()
+        .map(|cue| Group {
+            start: cue.start.total_millis(),
+            end: cue.end.total_millis().max(cue.start.total_millis()),
+            words: cue.text.split_whitespace().map(str::to_string).collect(),
+            cue,
+            edited: false,
+        })
+        .collect();
+    merge_short(&mut groups, options);
+    extend(&mut groups, options);
+    move_words(&mut groups, options);
+
+    groups
+        .into_iter()
+        .map(|mut group| {
+            if group.edited {
+                group.cue.text = group.words.join(" ");
+            }
+            if group.start != group.cue.start.total_millis() {
+                group.cue.start = TimeCode::from_millis(group.start)?;
+            }
+            if group.end != group.cue.end.total_millis() {
+                group.cue.end = TimeCode::from_millis(group.end)?;
+            }
+            Ok(group.cue)
+        })
+        .collect()
+}
+
+// Merges cues too short to read into a neighbour when the merged cue is
+// still slow enough to read
+fn merge_short(groups: &mut Vec<Group>, options: &RegroupOptions) {
+    let mut i = 0;
+    while i < groups.len() {
+        if groups[i].end - groups[i].start >= options.min_duration_ms {
+            i += 1;
+            continue;
+        }
+        let fits = |a: &Group, b: &Group| {
+            let words: Vec<String> = a.words.iter().chain(&b.words).cloned().collect();
+            // cues out of order or overlapping never fit
+            cps(&words, b.end.saturating_sub(a.start)) <= options.max_cps
+        };
+        if i + 1 < groups.len() && fits(&groups[i], &groups[i + 1]) {
+            let next = groups.remove(i + 1);
+            absorb(&mut groups[i], next);
+        } else if i > 0 && fits(&groups[i - 1], &groups[i]) {
+            let current = groups.remove(i);
+            absorb(&mut groups[i - 1], current);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+// Appends a later group onto an earlier one
+fn absorb(into: &mut Group, from: Group) {
+    into.end = into.end.max(from.end);
+    into.words.extend(from.words);
+    into.cue.notes.extend(from.cue.notes);
+    into.edited = true;
+}
+
+// Stretches cues that are too short or too fast into the silence after
+// them, up to the minimum gap before the next cue
+fn extend(groups: &mut [Group], options: &RegroupOptions) {
+    for i in 0..groups.len() {
+        let group = &groups[i];
+        let reading =
+            (text_chars(&group.words.join(" ")) as f64 * 1000.0 / options.max_cps).ceil() as u64;
+        let wanted = group.start + reading.max(options.min_duration_ms);
+        let limit = match groups.get(i + 1) {
+            Some(next) => next.start.saturating_sub(options.min_gap_ms),
+            None => wanted,
+        };
+        let end = wanted.min(limit);
+        if end > groups[i].end {
+            groups[i].end = end;
+        }
+    }
+}
+
+// Moves words between touching cues while that lowers the faster cue's
+// reading speed
+fn move_words(groups: &mut [Group], options: &RegroupOptions) {
+    let total: usize = groups.iter().map(|group| group.words.len()).sum();
+    for _ in 0..total {
+        let mut moved = false;
+        for i in 1..groups.len() {
+            let (left, right) = groups.split_at_mut(i);
+            let (a, b) = (&mut left[i - 1], &mut right[0]);
+            if b.start - a.end.min(b.start) > options.min_gap_ms {
+                continue;
+            }
+            let worst = a.cps().max(b.cps());
+            if worst <= options.max_cps {
+                continue;
+            }
+            if a.words.len() > 1 {
+                let word = a.words.pop().unwrap();
+                b.words.insert(0, word);
+                if a.cps().max(b.cps()) < worst {
+                    a.edited = true;
+                    b.edited = true;
+                    moved = true;
+                    continue;
+                }
+                let word = b.words.remove(0);
+                a.words.push(word);
+            }
+            if b.words.len() > 1 {
+                let word = b.words.remove(0);
+                a.words.push(word);
+                if a.cps().max(b.cps()) < worst {
+                    a.edited = true;
+                    b.edited = true;
+                    moved = true;
+                    continue;
+                }
+                let word = a.words.pop().unwrap();
+                b.words.insert(0, word);
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CueTrait;
+
+    fn cue(timing: &str, text: &str) -> Result<Cue, ParseError> {
+        format!("{}\n{}", timing, text).to_cue()
+    }
+
+    #[test]
+    fn short_cues_merge() -> Result<(), ParseError> {
+        let cues = vec![
+            cue("00:00:01.000 --> 00:00:01.300", "Oh.")?,
+            cue("00:00:01.350 --> 00:00:03.000", "Hello there")?,
+            cue("00:00:10.000 --> 00:00:12.000", "Later")?,
+        ];
+        let cues = regroup(cues, &RegroupOptions::default())?;
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text, "Oh. Hello there");
+        assert_eq!(cues[0].end.total_millis(), 3_000);
+        assert_eq!(cues[1].text, "Later");
+        assert_eq!(cues[1].start.as_str(), "00:00:10.000");
+        Ok(())
+    }
+
+    #[test]
+    fn cues_extend_into_gaps_but_keep_min_gap() -> Result<(), ParseError> {
+        let cues = vec![
+            cue("00:00:01.000 --> 00:00:02.000", "Twenty five characters ok")?,
+            cue("00:00:02.500 --> 00:00:04.000", "Next")?,
+        ];
+        let options = RegroupOptions::default();
+        let cues = regroup(cues, &options)?;
+        assert_eq!(cues[0].end.total_millis(), 2_500 - options.min_gap_ms);
+        // still too fast once stretched, so a word moves on
+        assert_eq!(cues[0].text, "Twenty five characters");
+        assert_eq!(cues[1].text, "ok Next");
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_order_cues_do_not_panic() -> Result<(), ParseError> {
+        let cues = vec![
+            cue("00:00:05.000 --> 00:00:05.300", "Late")?,
+            cue("00:00:01.000 --> 00:00:01.200", "Early")?,
+            cue("00:00:00.900 --> 00:00:04.000", "Overlapping")?,
+        ];
+        let cues = regroup(cues, &RegroupOptions::default())?;
+        assert!(cues.iter().all(|cue| cue.end >= cue.start));
+        Ok(())
+    }
+
+    #[test]
+    fn untouched_cues_are_kept() -> Result<(), ParseError> {
+        let cues = vec![cue("00:00:01,000 --> 00:00:03,000", "Slow\nand steady")?];
+        let cues = regroup(cues, &RegroupOptions::default())?;
+        assert_eq!(cues[0].text, "Slow\nand steady");
+        assert_eq!(cues[0].start.as_str(), "00:00:01,000");
+        Ok(())
+    }
+}
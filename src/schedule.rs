@@ -0,0 +1,228 @@
+//! tells media players when to show and hide cues
+//!
+//! A player feeds its playback position to a [`Scheduler`] from its own
+//! loop, whenever it likes, and gets back the cues to show and hide
+//! since the last position, in the order they happen. No timers or
+//! interval logic are needed on the player's side.
+
+use crate::track::Track;
+use crate::Cue;
+use std::time::Duration;
+
+/// A change to the cues on screen, see [`Scheduler::advance`]
+///
+/// Events are equal when they are about the very same cue of a track.
+#[derive(Debug, Clone, Copy)]
+pub enum CueEvent<'t> {
+    /// the cue's start time has been reached
+    Show(&'t Cue),
+    /// the cue's end time has been reached, it was shown before
+    Hide(&'t Cue),
+}
+
+impl PartialEq for CueEvent<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CueEvent::Show(a), CueEvent::Show(b)) | (CueEvent::Hide(a), CueEvent::Hide(b)) => {
+                std::ptr::eq(*a, *b)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Turns a playback clock into show and hide events for a track
+///
+/// ```
+/// use std::time::Duration;
+/// use title_parser::schedule::{CueEvent, Scheduler};
+/// use title_parser::track::Track;
+///
+/// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+/// let track = Track::parse(text).unwrap();
+/// let mut scheduler = Scheduler::new(&track);
+/// assert!(scheduler.advance(Duration::from_millis(500)).is_empty());
+/// assert_eq!(scheduler.next_change(), Some(Duration::from_secs(1)));
+/// let events = scheduler.advance(Duration::from_millis(3_200));
+/// assert_eq!(events, vec![
+///     CueEvent::Show(&track.cues[0]),
+///     CueEvent::Hide(&track.cues[0]),
+///     CueEvent::Show(&track.cues[1]),
+/// ]);
+/// ```
+pub struct Scheduler<'t> {
+    track: &'t Track,
+    // indexes of the cues by start time
+    order: Vec<usize>,
+    // position in `order` of the next cue to show
+    next: usize,
+    // indexes of the cues on screen
+    showing: Vec<usize>,
+    now: u64,
+}
+
+// Rank of events due at the same time: cues make room before others
+// show, and cues showing and ending at once still show first
+const HIDE: u8 = 0;
+const SHOW: u8 = 1;
+const HIDE_INSTANT: u8 = 2;
+
+impl<'t> Scheduler<'t> {
+    /// Creates a scheduler for playback starting at zero
+    pub fn new(track: &'t Track) -> Scheduler<'t> {
+        let mut order: Vec<usize> = (0..track.cues.len()).collect();
+        order.sort_by_key(|&i| track.cues[i].start.total_millis());
+        Scheduler {
+            track,
+            order,
+            next: 0,
+            showing: Vec::new(),
+            now: 0,
+        }
+    }
+
+    /// Moves the clock to `position`, returning every show and hide due
+    /// since the last position in the order they happen
+    ///
+    /// A cue that starts and ends between two positions is both shown
+    /// and hidden, so none are missed by a slow clock. Positions before
+    /// the last one are ignored, see [`Scheduler::seek`] for jumping
+    /// back.
+    pub fn advance(&mut self, position: Duration) -> Vec<CueEvent<'t>> {
+        let now = millis(position);
+        if now < self.now {
+            return Vec::new();
+        }
+        self.now = now;
+        let cues = &self.track.cues;
+        let start = |i: usize| cues[i].start.total_millis();
+        let end = |i: usize| cues[i].end.total_millis();
+        // (when, rank, index) of every event due
+        let mut due: Vec<(u64, u8, usize)> = Vec::new();
+        self.showing.retain(|&i| {
+            let ended = end(i) <= now;
+            if ended {
+                due.push((end(i), HIDE, i));
+            }
+            !ended
+        });
+        while let Some(&i) = self.order.get(self.next).filter(|&&i| start(i) <= now) {
+            self.next += 1;
+            due.push((start(i), SHOW, i));
+            if end(i) > now {
+                self.showing.push(i);
+            } else if end(i) > start(i) {
+                due.push((end(i), HIDE, i));
+            } else {
+                due.push((start(i), HIDE_INSTANT, i));
+            }
+        }
+        due.sort_unstable();
+        due.into_iter()
+            .map(|(_, rank, i)| match rank {
+                SHOW => CueEvent::Show(&cues[i]),
+                _ => CueEvent::Hide(&cues[i]),
+            })
+            .collect()
+    }
+
+    /// Jumps the clock to `position`, in either direction, returning
+    /// hides for the cues on screen followed by shows for the cues on
+    /// screen at `position`
+    ///
+    /// Cues that started and ended in between are skipped.
+    pub fn seek(&mut self, position: Duration) -> Vec<CueEvent<'t>> {
+        let cues = &self.track.cues;
+        let mut events: Vec<CueEvent<'t>> = self
+            .showing
+            .drain(..)
+            .map(|i| CueEvent::Hide(&cues[i]))
+            .collect();
+        let now = millis(position);
+        self.now = now;
+        self.next = self
+            .order
+            .partition_point(|&i| cues[i].start.total_millis() <= now);
+        for &i in &self.order[..self.next] {
+            if cues[i].end.total_millis() > now {
+                self.showing.push(i);
+                events.push(CueEvent::Show(&cues[i]));
+            }
+        }
+        events
+    }
+
+    /// When the next show or hide is due, or `None` once every cue has
+    /// been shown and hidden
+    pub fn next_change(&self) -> Option<Duration> {
+        let cues = &self.track.cues;
+        let hide = self.showing.iter().map(|&i| cues[i].end.total_millis());
+        let show = self
+            .order
+            .get(self.next)
+            .map(|&i| cues[i].start.total_millis());
+        hide.chain(show).min().map(Duration::from_millis)
+    }
+
+    /// The cues on screen, in the order they were shown
+    pub fn showing(&self) -> impl Iterator<Item = &'t Cue> + '_ {
+        self.showing.iter().map(|&i| &self.track.cues[i])
+    }
+}
+
+fn millis(position: Duration) -> u64 {
+    u64::try_from(position.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseError;
+
+    fn texts(events: &[CueEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                CueEvent::Show(cue) => format!("+{}", cue.text),
+                CueEvent::Hide(cue) => format!("-{}", cue.text),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_and_instant_cues() -> Result<(), ParseError> {
+        let text = "1\n00:00:01,000 --> 00:00:05,000\nA\n\n2\n00:00:02,000 --> 00:00:03,000\nB\n\n3\n00:00:03,000 --> 00:00:03,000\nC\n\n4\n00:00:05,000 --> 00:00:06,000\nD\n";
+        let track = Track::parse(text)?;
+        let mut scheduler = Scheduler::new(&track);
+        assert_eq!(
+            texts(&scheduler.advance(Duration::from_millis(2_500))),
+            ["+A", "+B"]
+        );
+        assert_eq!(
+            texts(&scheduler.advance(Duration::from_secs(5))),
+            ["-B", "+C", "-C", "-A", "+D"]
+        );
+        assert!(scheduler.advance(Duration::from_secs(1)).is_empty());
+        assert_eq!(scheduler.showing().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn seeking_back_restores_the_screen() -> Result<(), ParseError> {
+        let text = "1\n00:00:01,000 --> 00:00:05,000\nA\n\n2\n00:00:02,000 --> 00:00:03,000\nB\n\n3\n00:00:06,000 --> 00:00:07,000\nC\n";
+        let track = Track::parse(text)?;
+        let mut scheduler = Scheduler::new(&track);
+        scheduler.advance(Duration::from_millis(6_500));
+        assert_eq!(
+            texts(&scheduler.seek(Duration::from_millis(2_500))),
+            ["-C", "+A", "+B"]
+        );
+        assert_eq!(scheduler.next_change(), Some(Duration::from_secs(3)));
+        assert_eq!(
+            texts(&scheduler.advance(Duration::from_secs(8))),
+            ["-B", "-A", "+C", "-C"]
+        );
+        assert_eq!(scheduler.next_change(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! immutable, cheaply cloned tracks for sharing between threads
+//!
+//! A `SharedTrack` never changes once built. Edits return a new version
+//! that shares every unchanged cue with the old one, so servers can keep
+//! serving readers from one version while editors produce the next.
+
+use crate::style::StyleSheet;
+use crate::track::{Confidence, Format, Track};
+use crate::Cue;
+use std::sync::Arc;
+
+// Track level data that edits to cues never touch
+struct Info {
+    format: Format,
+    description: Option<String>,
+    metadata: Vec<String>,
+    styles: StyleSheet,
+    encoding: Option<&'static str>,
+    encoding_confidence: Option<Confidence>,
+}
+
+/// A persistent version of a `Track`, see the module documentation
+///
+/// ```
+/// use title_parser::CueTrait;
+/// use title_parser::shared::SharedTrack;
+/// use title_parser::track::Track;
+///
+/// let text = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHello\n\n00:00:03.000 --> 00:00:04.000\nWorld\n";
+/// let v1 = SharedTrack::from(Track::parse(text).unwrap());
+/// let fixed = "00:00:03.000 --> 00:00:04.000\nthere".to_cue().unwrap();
+/// let v2 = v1.with_cue(1, fixed).unwrap();
+///
+/// assert_eq!(v1.get(1).unwrap().text, "World");
+/// assert_eq!(v2.get(1).unwrap().text, "there");
+/// assert!(v1.shares_cue(&v2, 0));
+/// ```
+#[derive(Clone)]
+pub struct SharedTrack {
+    info: Arc<Info>,
+    cues: Arc<Vec<Arc<Cue>>>,
+}
+
+impl From<Track> for SharedTrack {
+    fn from(track: Track) -> Self {
+        SharedTrack {
+            info: Arc::new(Info {
+                format: track.format,
+                description: track.description,
+                metadata: track.metadata,
+                styles: track.styles,
+                encoding: track.encoding,
+                encoding_confidence: track.encoding_confidence,
+            }),
+            cues: Arc::new(track.cues.into_iter().map(Arc::new).collect()),
+        }
+    }
+}
+
+impl SharedTrack {
+    /// format the track was parsed from
+    pub fn format(&self) -> Format {
+        self.info.format
+    }
+
+    /// text following the `WEBVTT` signature, if any
+    pub fn description(&self) -> Option<&str> {
+        self.info.description.as_deref()
+    }
+
+    /// remaining lines of the WebVTT header block
+    pub fn metadata(&self) -> &[String] {
+        &self.info.metadata
+    }
+
+    /// styles defined by the track
+    pub fn styles(&self) -> &StyleSheet {
+        &self.info.styles
+    }
+
+    /// name of the encoding the track was decoded from, if known
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.info.encoding
+    }
+
+    /// how sure detection was about the encoding, if known
+    pub fn encoding_confidence(&self) -> Option<Confidence> {
+        self.info.encoding_confidence
+    }
+
+    /// number of cues in this version
+    pub fn len(&self) -> usize {
+        self.cues.len()
+    }
+
+    /// whether this version has no cues
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    /// cue at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<&Cue> {
+        self.cues.get(index).map(|cue| cue.as_ref())
+    }
+
+    /// iterates over the cues of this version in order
+    pub fn iter(&self) -> impl Iterator<Item = &Cue> {
+        self.cues.iter().map(|cue| cue.as_ref())
+    }
+
+    /// Returns a new version with the cue at `index` replaced, or `None`
+    /// if `index` is out of bounds
+    pub fn with_cue(&self, index: usize, cue: Cue) -> Option<SharedTrack> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.edit(|cues| cues[index] = Arc::new(cue)))
+    }
+
+    /// Returns a new version with `cue` inserted at `index`, or `None`
+    /// if `index` is greater than the number of cues
+    pub fn with_inserted(&self, index: usize, cue: Cue) -> Option<SharedTrack> {
+        if index > self.len() {
+            return None;
+        }
+        Some(self.edit(|cues| cues.insert(index, Arc::new(cue))))
+    }
+
+    /// Returns a new version without the cue at `index`, or `None` if
+    /// `index` is out of bounds
+    pub fn without(&self, index: usize) -> Option<SharedTrack> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.edit(|cues| {
+            cues.remove(index);
+        }))
+    }
+
+    /// Checks whether both versions hold the very same cue allocation at
+    /// `index`, meaning the cue was not touched between them
+    pub fn shares_cue(&self, other: &SharedTrack, index: usize) -> bool {
+        match (self.cues.get(index), other.cues.get(index)) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    // Copies the list of cue pointers (not the cues) and applies an edit
+    fn edit<F: FnOnce(&mut Vec<Arc<Cue>>)>(&self, f: F) -> SharedTrack {
+        let mut cues: Vec<Arc<Cue>> = self.cues.as_ref().clone();
+        f(&mut cues);
+        SharedTrack {
+            info: Arc::clone(&self.info),
+            cues: Arc::new(cues),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CueTrait;
+    use crate::ParseError;
+
+    fn example() -> Result<SharedTrack, ParseError> {
+        Ok(SharedTrack::from(Track::parse(include_str!(
+            "example.vtt"
+        ))?))
+    }
+
+    #[test]
+    fn edits_share_unchanged_cues() -> Result<(), ParseError> {
+        let v1 = example()?;
+        let cue = "00:00:00.000 --> 00:00:01.000\nintro".to_cue()?;
+        let v2 = v1.with_inserted(0, cue).unwrap();
+        let v3 = v2.without(1).unwrap();
+        assert_eq!(v1.len(), 3);
+        assert_eq!(v2.len(), 4);
+        assert_eq!(v3.len(), 3);
+        assert_eq!(v3.get(0).unwrap().text, "intro");
+        assert!(Arc::ptr_eq(&v1.cues[1], &v3.cues[1]));
+        let cue = "00:00:00.000 --> 00:00:01.000\nlate".to_cue()?;
+        assert!(v1.with_cue(3, cue).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn readers_on_other_threads() -> Result<(), ParseError> {
+        let track = example()?;
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let track = track.clone();
+                std::thread::spawn(move || track.iter().count())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 3);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! splits documents holding several tracks on one timeline
+//!
+//! User uploads are often two tracks naively appended or interleaved
+//! into one file, which shows up as timestamps jumping back (usually to
+//! zero) partway through.
+
+use crate::track::Track;
+use crate::Cue;
+
+/// A point where the timeline jumps back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reset {
+    /// index in the original track of the first cue after the jump
+    pub cue: usize,
+    /// start of the cue before the jump, in milliseconds
+    pub from_ms: u64,
+    /// start of the cue after the jump, in milliseconds
+    pub to_ms: u64,
+}
+
+/// Result of [`Track::split_resets`]
+#[derive(Debug)]
+pub struct Split {
+    /// the separated tracks, in order of their first cue
+    pub tracks: Vec<Track>,
+    /// every jump back that was found, whether or not it started a new
+    /// track
+    pub resets: Vec<Reset>,
+}
+
+impl Track {
+    /// Splits the track wherever the timeline jumps back by more than
+    /// `min_jump_ms`
+    ///
+    /// Each cue continues the track whose last cue started most recently
+    /// before it, so chunks of two interleaved tracks end up as two
+    /// tracks rather than one per chunk. Only a jump starts a new track;
+    /// smaller steps back are left where they are, as cues slightly out
+    /// of order are common in otherwise healthy files.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:10:00,000 --> 00:10:02,000\nfirst\n\n2\n00:20:00,000 --> 00:20:02,000\nend\n\n1\n00:00:01,000 --> 00:00:02,000\nsecond\n";
+    /// let split = Track::parse(text).unwrap().split_resets(30_000);
+    /// assert_eq!(split.tracks.len(), 2);
+    /// assert_eq!(split.resets[0].cue, 2);
+    /// assert_eq!(split.tracks[1].cues[0].text, "second");
+    /// ```
+    pub fn split_resets(self, min_jump_ms: u64) -> Split {
+        let Track {
+            format,
+            description,
+            metadata,
+            styles,
+            cues,
+            encoding,
+            encoding_confidence,
+        } = self;
+
+        let mut resets = Vec::new();
+        let mut separated: Vec<Vec<Cue>> = Vec::new();
+        // start of the previous cue and the track it went to
+        let mut previous: Option<(u64, usize)> = None;
+        for (index, cue) in cues.into_iter().enumerate() {
+            let start = cue.start.total_millis();
+            let jump = previous.filter(|&(from, _)| from > start.saturating_add(min_jump_ms));
+            if let Some((from, _)) = jump {
+                resets.push(Reset {
+                    cue: index,
+                    from_ms: from,
+                    to_ms: start,
+                });
+            }
+            let fits = (0..separated.len())
+                .filter(|&i| last_start(&separated[i]) <= start)
+                .max_by_key(|&i| last_start(&separated[i]));
+            let track = match (fits, previous) {
+                (Some(i), _) => i,
+                (None, Some((_, i))) if jump.is_none() => i,
+                _ => {
+                    separated.push(Vec::new());
+                    separated.len() - 1
+                }
+            };
+            separated[track].push(cue);
+            previous = Some((start, track));
+        }
+
+        let tracks = separated
+            .into_iter()
+            .map(|cues| Track {
+                format,
+                description: description.clone(),
+                metadata: metadata.clone(),
+                styles: styles.clone(),
+                cues,
+                encoding,
+                encoding_confidence,
+            })
+            .collect();
+        Split { tracks, resets }
+    }
+}
+
+fn last_start(cues: &[Cue]) -> u64 {
+    cues.last().map_or(0, |cue| cue.start.total_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseError;
+
+    fn srt(starts: &[u64]) -> String {
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                format!(
+                    "{}\n00:{:02}:00,000 --> 00:{:02}:30,000\ncue {}\n\n",
+                    i + 1,
+                    s,
+                    s,
+                    i
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleaved_chunks_become_two_tracks() -> Result<(), ParseError> {
+        let track = Track::parse(&srt(&[0, 1, 2, 0, 1, 3, 4, 2, 3]))?;
+        let split = track.split_resets(30_000);
+        assert_eq!(split.resets.len(), 2);
+        let texts: Vec<Vec<&str>> = split
+            .tracks
+            .iter()
+            .map(|t| t.cues.iter().map(|c| c.text.as_str()).collect())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                vec!["cue 0", "cue 1", "cue 2", "cue 5", "cue 6"],
+                vec!["cue 3", "cue 4", "cue 7", "cue 8"],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn small_jumps_are_kept() -> Result<(), ParseError> {
+        let track = Track::parse(include_str!("jp.srt"))?;
+        let split = track.split_resets(30_000);
+        assert!(split.resets.is_empty());
+        assert_eq!(split.tracks.len(), 1);
+        assert_eq!(split.tracks[0].cues.len(), 5);
+        // no jump is larger than the largest threshold
+        let track = Track::parse(&srt(&[3, 0]))?;
+        assert_eq!(track.split_resets(u64::MAX).tracks.len(), 1);
+        Ok(())
+    }
+}
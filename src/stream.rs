@@ -0,0 +1,223 @@
+//! parses live caption streams as their bytes arrive
+//!
+//! Live WebVTT is sent over chunked HTTP, and packets fall wherever
+//! they like: in the middle of a cue, a line or even a UTF-8 character.
+//! A [`StreamingParser`] keeps whatever is incomplete until the rest
+//! arrives and hands out every cue once it is known to be complete.
+
+use crate::track::{
+    is_vtt_extra_block, note_text, parse_cues, strip_bom, vtt_signature, Format, LineBlocks,
+};
+use crate::{exceeds, Cue, ErrorKind, ParseError, ParseOptions, Span};
+use std::collections::VecDeque;
+
+/// Push parser for SRT and WebVTT streams
+///
+/// A cue is complete once the blank line after it arrives, or the
+/// stream is [finished](StreamingParser::finish), since more text lines
+/// could follow until then. Invalid UTF-8 is replaced with `�`. A cue
+/// that fails to parse is reported and the stream carries on, apart
+/// from `Limits` being exceeded, after which nothing more is parsed.
+///
+/// ```
+/// use title_parser::stream::StreamingParser;
+///
+/// let mut parser = StreamingParser::new();
+/// parser.feed(b"WEBVTT\n\n00:00:01.000 --> 00:00");
+/// parser.feed(b":02.000\nCaf\xC3");
+/// assert!(parser.poll().is_none());
+/// parser.feed(b"\xA9\n\n00:00:03.000 --> 00:00:04.000\nLater");
+/// assert_eq!(parser.poll().unwrap().unwrap().text, "Café");
+/// assert!(parser.poll().is_none());
+/// parser.finish();
+/// assert_eq!(parser.poll().unwrap().unwrap().text, "Later");
+/// ```
+pub struct StreamingParser {
+    options: ParseOptions,
+    // bytes of the line still arriving
+    partial: Vec<u8>,
+    lines: LineBlocks,
+    // known once the first block is complete
+    format: Option<Format>,
+    // notes waiting for the cue they precede
+    notes: Vec<String>,
+    ready: VecDeque<Result<Cue, ParseError>>,
+    count: usize,
+    stopped: bool,
+}
+
+impl Default for StreamingParser {
+    fn default() -> StreamingParser {
+        StreamingParser::new()
+    }
+}
+
+impl StreamingParser {
+    /// Creates a parser for a new stream
+    pub fn new() -> StreamingParser {
+        StreamingParser::with_options(&ParseOptions::default())
+    }
+
+    /// Creates a parser for a new stream using the given options
+    ///
+    /// `Limits::max_input_bytes` applies to each block and to the line
+    /// still arriving, since a stream as a whole has no end to measure.
+    pub fn with_options(options: &ParseOptions) -> StreamingParser {
+        StreamingParser {
+            options: options.clone(),
+            partial: Vec::new(),
+            lines: LineBlocks::new(options.limits.max_input_bytes),
+            format: None,
+            notes: Vec::new(),
+            ready: VecDeque::new(),
+            count: 0,
+            stopped: false,
+        }
+    }
+
+    /// Format of the stream, once its first block has arrived
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+
+    /// Adds the next chunk of the stream
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if self.stopped {
+            return;
+        }
+        self.partial.extend_from_slice(bytes);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            self.push_line(&line);
+        }
+        let max = self.options.limits.max_input_bytes;
+        if !self.stopped && exceeds(self.partial.len(), max) {
+            self.stop(ErrorKind::InputTooLarge(max.unwrap()).into());
+        }
+    }
+
+    /// Marks the end of the stream, completing the last cue
+    pub fn finish(&mut self) {
+        let partial = std::mem::take(&mut self.partial);
+        self.push_line(&partial);
+        if self.stopped {
+            return;
+        }
+        if let Some((at, block)) = self.lines.finish() {
+            self.push_block(at, &block);
+        }
+    }
+
+    /// Takes the next complete cue, or the error it failed with, if one
+    /// has arrived
+    pub fn poll(&mut self) -> Option<Result<Cue, ParseError>> {
+        self.ready.pop_front()
+    }
+
+    fn push_line(&mut self, line: &[u8]) {
+        if self.stopped {
+            return;
+        }
+        match self.lines.push_line(&String::from_utf8_lossy(line)) {
+            Ok(Some((at, block))) => self.push_block(at, &block),
+            Ok(None) => {}
+            Err(e) => self.stop(e),
+        }
+    }
+
+    fn push_block(&mut self, at: Span, block: &str) {
+        let (mut at, mut block) = (at, block);
+        if self.format.is_none() {
+            (at, block) = strip_bom(at, block);
+            if vtt_signature(block).is_some() {
+                self.format = Some(Format::WebVtt);
+                return;
+            }
+            self.format = Some(Format::Srt);
+        }
+        if self.format == Some(Format::WebVtt) && is_vtt_extra_block(block) {
+            self.notes.extend(note_text(block));
+            return;
+        }
+        for result in parse_cues(at, block, &self.options) {
+            let (at, mut cue) = match result {
+                Ok((at, cue, _)) => (at, cue),
+                Err(e) => {
+                    self.ready.push_back(Err(e));
+                    continue;
+                }
+            };
+            self.count += 1;
+            let max_cues = self.options.limits.max_cues;
+            if let Some(max) = max_cues.filter(|max| self.count > *max) {
+                return self.stop(ParseError::new(ErrorKind::TooManyCues(max), at));
+            }
+            cue.notes = std::mem::take(&mut self.notes);
+            self.ready.push_back(Ok(cue));
+        }
+    }
+
+    fn stop(&mut self, error: ParseError) {
+        self.ready.push_back(Err(error));
+        self.stopped = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::Track;
+
+    #[test]
+    fn byte_at_a_time_matches_whole_document() -> Result<(), ParseError> {
+        let text = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\nフフッ\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nWorld\r\n";
+        let mut parser = StreamingParser::new();
+        let mut cues = Vec::new();
+        for byte in text.as_bytes() {
+            parser.feed(&[*byte]);
+            cues.extend(std::iter::from_fn(|| parser.poll()));
+        }
+        parser.finish();
+        cues.extend(std::iter::from_fn(|| parser.poll()));
+        let cues: Vec<Cue> = cues.into_iter().collect::<Result<_, _>>()?;
+        assert_eq!(parser.format(), Some(Format::Srt));
+        let expected = Track::parse(text)?.cues;
+        let texts = |cues: &[Cue]| cues.iter().map(|cue| cue.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&cues), texts(&expected));
+        assert_eq!(cues[1].start, expected[1].start);
+        Ok(())
+    }
+
+    #[test]
+    fn blocks_without_blank_lines_hit_the_limit() {
+        let options = ParseOptions {
+            limits: crate::Limits {
+                max_input_bytes: Some(1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut parser = StreamingParser::with_options(&options);
+        parser.feed(b"00:00:01.000 --> 00:00:02.000\n");
+        for _ in 0..100_000 {
+            parser.feed(b"more text\n");
+        }
+        let err = parser.poll().unwrap().unwrap_err();
+        assert_eq!(
+            (err.kind, err.span.line),
+            (ErrorKind::InputTooLarge(1024), 101)
+        );
+        assert!(parser.poll().is_none());
+    }
+
+    #[test]
+    fn bad_cues_do_not_end_the_stream() {
+        let mut parser = StreamingParser::new();
+        parser.feed(b"WEBVTT\n\nNOTE hi\n\n00:00:01.000 --> 00:00\nBad\n\n");
+        parser.feed(b"00:00:03.000 --> 00:00:04.000\nGood\n\n");
+        let err = parser.poll().unwrap().unwrap_err();
+        assert_eq!((err.kind, err.span.line), (ErrorKind::InvalidTimecode, 5));
+        let cue = parser.poll().unwrap().unwrap();
+        assert_eq!((cue.text.as_str(), cue.notes.len()), ("Good", 1));
+    }
+}
@@ -0,0 +1,227 @@
+//! named styles shared by every subtitle format
+//!
+//! Formats style text in their own way: WebVTT with CSS in `STYLE`
+//! blocks, ASS with `[V4+ Styles]` and TTML with `<styling>`. Each format
+//! reads its styles into a `StyleSheet` and writes them back out of one,
+//! so styling survives conversion between any pair of formats. Only
+//! WebVTT is read and written so far.
+
+use std::fmt::Write;
+
+/// Horizontal alignment of styled text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    /// aligned to the left edge, or the start of right-to-left text
+    Left,
+    /// centered
+    Center,
+    /// aligned to the right edge
+    Right,
+}
+
+/// A named set of text properties, with `None` / `false` meaning the
+/// property is left to the player
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    /// name cues refer to the style by, empty for the style applying to
+    /// every cue
+    pub name: String,
+    /// font family, such as `Arial` or `sans-serif`
+    pub font: Option<String>,
+    /// font size as written in the source format, such as `120%`
+    pub size: Option<String>,
+    /// text color as written in the source format, such as `#ffff00`
+    pub color: Option<String>,
+    /// whether text is bold
+    pub bold: bool,
+    /// whether text is italic
+    pub italic: bool,
+    /// horizontal alignment of the text
+    pub align: Option<Align>,
+}
+
+impl Style {
+    // Copies every property set on `other` over this style
+    fn apply(&mut self, other: Style) {
+        self.font = other.font.or(self.font.take());
+        self.size = other.size.or(self.size.take());
+        self.color = other.color.or(self.color.take());
+        self.bold |= other.bold;
+        self.italic |= other.italic;
+        self.align = other.align.or(self.align);
+    }
+}
+
+/// Styles of a track, see the module documentation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyleSheet {
+    /// styles in the order they were first defined
+    pub styles: Vec<Style>,
+}
+
+impl StyleSheet {
+    /// Style called `name`, if there is one
+    pub fn get(&self, name: &str) -> Option<&Style> {
+        self.styles.iter().find(|style| style.name == name)
+    }
+
+    /// Whether no styles are defined
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+
+    /// Adds a style, combining it with any earlier style of the same name
+    /// where later properties win
+    pub fn insert(&mut self, style: Style) {
+        match self.styles.iter_mut().find(|s| s.name == style.name) {
+            Some(existing) => existing.apply(style),
+            None => self.styles.push(style),
+        }
+    }
+
+    /// Reads the `::cue` rules of WebVTT `STYLE` CSS
+    ///
+    /// `::cue` styles every cue and `::cue(.name)` the cues using a class.
+    /// Rules for other selectors and properties without a counterpart
+    /// in the other formats are skipped.
+    ///
+    /// ```
+    /// use title_parser::style::StyleSheet;
+    ///
+    /// let css = "::cue { color: white }\n::cue(.loud) { font-weight: bold; font-size: 120% }";
+    /// let sheet = StyleSheet::from_vtt(css);
+    /// assert_eq!(sheet.get("").unwrap().color.as_deref(), Some("white"));
+    /// assert!(sheet.get("loud").unwrap().bold);
+    /// ```
+    pub fn from_vtt(css: &str) -> StyleSheet {
+        let mut sheet = StyleSheet::default();
+        let mut rest = css;
+        while let Some(open) = rest.find('{') {
+            let selector = rest[..open].trim();
+            let close = rest[open..].find('}').map_or(rest.len(), |i| open + i);
+            let body = &rest[open + 1..close];
+            rest = rest.get(close + 1..).unwrap_or("");
+            let Some(name) = cue_class(selector) else {
+                continue;
+            };
+            let mut style = Style {
+                name: name.to_string(),
+                ..Style::default()
+            };
+            for (property, value) in body.split(';').filter_map(|d| d.split_once(':')) {
+                let value = value.trim();
+                match property.trim().to_ascii_lowercase().as_str() {
+                    "font-family" => style.font = Some(value.to_string()),
+                    "font-size" => style.size = Some(value.to_string()),
+                    "color" => style.color = Some(value.to_string()),
+                    "font-weight" => {
+                        style.bold = value == "bold" || value.parse::<u32>().is_ok_and(|w| w >= 600)
+                    }
+                    "font-style" => style.italic = value == "italic" || value == "oblique",
+                    "text-align" => {
+                        style.align = match value {
+                            "left" | "start" => Some(Align::Left),
+                            "center" => Some(Align::Center),
+                            "right" | "end" => Some(Align::Right),
+                            _ => None,
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            sheet.insert(style);
+        }
+        sheet
+    }
+
+    /// Writes the styles as CSS for a WebVTT `STYLE` block
+    ///
+    /// ```
+    /// use title_parser::style::{Style, StyleSheet};
+    ///
+    /// let mut sheet = StyleSheet::default();
+    /// sheet.insert(Style { name: "loud".to_string(), bold: true, ..Style::default() });
+    /// assert_eq!(sheet.to_vtt(), "::cue(.loud) { font-weight: bold; }\n");
+    /// ```
+    pub fn to_vtt(&self) -> String {
+        let mut out = String::new();
+        for style in &self.styles {
+            if style.name.is_empty() {
+                out.push_str("::cue {");
+            } else {
+                let _ = write!(out, "::cue(.{}) {{", style.name);
+            }
+            let mut property = |name: &str, value: &str| {
+                let _ = write!(out, " {}: {};", name, value);
+            };
+            if let Some(font) = &style.font {
+                property("font-family", font);
+            }
+            if let Some(size) = &style.size {
+                property("font-size", size);
+            }
+            if let Some(color) = &style.color {
+                property("color", color);
+            }
+            if style.bold {
+                property("font-weight", "bold");
+            }
+            if style.italic {
+                property("font-style", "italic");
+            }
+            if let Some(align) = style.align {
+                let value = match align {
+                    Align::Left => "left",
+                    Align::Center => "center",
+                    Align::Right => "right",
+                };
+                property("text-align", value);
+            }
+            out.push_str(" }\n");
+        }
+        out
+    }
+}
+
+// Returns the class a `::cue` selector applies to, empty for all cues
+fn cue_class(selector: &str) -> Option<&str> {
+    let rest = selector.strip_prefix("::cue")?.trim();
+    if rest.is_empty() {
+        return Some("");
+    }
+    let class = rest.strip_prefix('(')?.strip_suffix(')')?.trim();
+    let class = class.strip_prefix('.')?;
+    let is_name = !class.is_empty()
+        && class
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if is_name {
+        Some(class)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vtt_round_trip() {
+        let css = "::cue { font-family: sans-serif; text-align: center }\n::cue(.yellow) { color: #ffff00; font-style: italic; }\n";
+        let sheet = StyleSheet::from_vtt(css);
+        assert_eq!(sheet.styles.len(), 2);
+        assert_eq!(sheet.get("").unwrap().align, Some(Align::Center));
+        assert_eq!(StyleSheet::from_vtt(&sheet.to_vtt()), sheet);
+    }
+
+    #[test]
+    fn later_rules_win() {
+        let css = "::cue(.a) { color: red; font-weight: 700 }\n::cue(#id) { color: blue }\n::cue(.a) { color: lime }";
+        let sheet = StyleSheet::from_vtt(css);
+        assert_eq!(sheet.styles.len(), 1);
+        let style = sheet.get("a").unwrap();
+        assert_eq!(style.color.as_deref(), Some("lime"));
+        assert!(style.bold);
+    }
+}
@@ -1,6 +1,17 @@
 //! enables programmatic usage of SRT / WebVTT timecodes
 
+// the public fields are deprecated for users of the crate, this module
+// keeps them consistent
+#![allow(deprecated)]
+
+use crate::{ErrorKind, ParseError};
+use once_cell::sync::Lazy;
 use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::time::Duration;
 
 /// trait to implement for types that can be converted to
 /// a `TimeCode`
@@ -8,27 +19,35 @@ pub trait TimeCodeTrait {
     /// Attempts to convert the type to `TimeCode`
     ///
     /// ```
+    /// use title_parser::ErrorKind;
     /// use title_parser::timecode::{TimeCodeTrait};
     ///
     /// let tc_string = "00:01:14.815";
     /// let tc = tc_string.to_timecode().unwrap();
-    /// assert_eq!(tc.string, tc_string);
+    /// assert_eq!(tc.as_str(), tc_string);
     ///
     /// // bad timecode
     /// let tc_string = "00:01:67.815";
     /// let tc = tc_string.to_timecode();
-    /// assert_eq!(tc, Err("invalid timecode".to_string()));
+    /// assert_eq!(tc.unwrap_err().kind, ErrorKind::InvalidTimecode);
     /// ```
-    fn to_timecode(&self) -> Result<TimeCode, String>;
+    fn to_timecode(&self) -> Result<TimeCode, ParseError>;
 }
 
+// `\d` would match any Unicode digit, which `u32::from_str` rejects
+static STRICT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(([0-9]{2,4}):)?([0-5][0-9]):([0-5][0-9])[\.,]([0-9]{3})$")
+        .expect("failed to compile regex")
+});
+
+static LENIENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(([0-9]{1,4}):)?([0-9]{1,2}):([0-9]{1,2})[\.,]([0-9]{1,3})$")
+        .expect("failed to compile regex")
+});
+
 impl TimeCodeTrait for str {
-    fn to_timecode(&self) -> Result<TimeCode, String> {
-        let re = Regex::new(r"^((\d{2,4}):)?([0-5][0-9]):([0-5][0-9])[\.,](\d{3})$")
-            .expect("failed to compile regex");
-        let caps = re
-            .captures(self)
-            .ok_or_else(|| "invalid timecode".to_string())?;
+    fn to_timecode(&self) -> Result<TimeCode, ParseError> {
+        let caps = STRICT.captures(self).ok_or(ErrorKind::InvalidTimecode)?;
 
         // it should be safe to unwrap() these values
         let hh: u32;
@@ -49,30 +68,206 @@ impl TimeCodeTrait for str {
     }
 }
 
+// Parses timecodes the way players do, accepting unpadded fields and
+// one or two digit milliseconds, which are read as a fraction of a
+// second so `00:00:01,5` is 1.5 seconds
+pub(crate) fn parse_lenient(input: &str) -> Result<TimeCode, ParseError> {
+    let caps = LENIENT.captures(input).ok_or(ErrorKind::InvalidTimecode)?;
+    let field = |i: usize| -> Result<u32, ParseError> {
+        caps.get(i).map_or(Ok(0), |m| {
+            m.as_str()
+                .parse()
+                .map_err(|_| ErrorKind::InvalidTimecode.into())
+        })
+    };
+    let fraction = caps.get(5).map_or("", |m| m.as_str());
+    let (mm, ss) = (field(3)?, field(4)?);
+    if mm > 59 || ss > 59 {
+        return Err(ErrorKind::InvalidTimecode.into());
+    }
+    Ok(TimeCode {
+        string: input.to_string(),
+        hh: field(2)?,
+        mm,
+        ss,
+        ttt: field(5)? * 10u32.pow(3 - fraction.len() as u32),
+    })
+}
+
 /// A TimeCode represents any valid SRT or VTT timestamps used for cue timing
 /// such as `00:01:14.815` or ``01:14.815``
 ///
+/// Read the fields through the accessor methods and change them with
+/// the setters, which keep the string in step with the fields. The
+/// public fields are deprecated and will become private, as writing to
+/// them leaves the string stale.
+///
+/// ```
+/// use title_parser::timecode::TimeCodeTrait;
+///
+/// let mut tc = "01:14,815".to_timecode().unwrap();
+/// assert_eq!((tc.minutes(), tc.seconds(), tc.millis()), (1, 14, 815));
+/// tc.set_minutes(2).unwrap();
+/// assert_eq!(tc.as_str(), "02:14,815");
+/// assert!(tc.set_seconds(60).is_err());
+/// ```
+///
+/// TimeCodes order by the time they stand for, so cues can be sorted
+/// and binary searched by their timing. Equal times written differently,
+/// like `01:14.815` and `00:01:14,815`, are not equal but order next to
+/// each other, by their strings.
+///
+/// ```
+/// use title_parser::timecode::TimeCodeTrait;
+///
+/// let mut times = vec!["10:00.000".to_timecode().unwrap(), "00:09:59,999".to_timecode().unwrap()];
+/// times.sort();
+/// assert_eq!(times[0].as_str(), "00:09:59,999");
+/// ```
 #[non_exhaustive]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TimeCode {
     /// copy of string used to create TimeCode
+    #[deprecated(note = "use `TimeCode::as_str`")]
     pub string: String,
     /// hours field from timecode
+    #[deprecated(note = "use `TimeCode::hours` and `TimeCode::set_hours`")]
     pub hh: u32,
     /// minutes field from timecode
+    #[deprecated(note = "use `TimeCode::minutes` and `TimeCode::set_minutes`")]
     pub mm: u32,
     /// seconds field from timecode
+    #[deprecated(note = "use `TimeCode::seconds` and `TimeCode::set_seconds`")]
     pub ss: u32,
     /// milliseconds field from timecode
+    #[deprecated(note = "use `TimeCode::millis` and `TimeCode::set_millis`")]
     pub ttt: u32,
 }
 
+impl Ord for TimeCode {
+    fn cmp(&self, other: &TimeCode) -> Ordering {
+        self.total_millis()
+            .cmp(&other.total_millis())
+            .then_with(|| self.string.cmp(&other.string))
+    }
+}
+
+impl PartialOrd for TimeCode {
+    fn partial_cmp(&self, other: &TimeCode) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl TimeCode {
+    /// The timecode as written, or as last rendered by a setter
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// Hours field of the timecode
+    pub fn hours(&self) -> u32 {
+        self.hh
+    }
+
+    /// Minutes field of the timecode
+    pub fn minutes(&self) -> u32 {
+        self.mm
+    }
+
+    /// Seconds field of the timecode
+    pub fn seconds(&self) -> u32 {
+        self.ss
+    }
+
+    /// Milliseconds field of the timecode
+    pub fn millis(&self) -> u32 {
+        self.ttt
+    }
+
+    /// Sets the hours field, at most 9999
+    pub fn set_hours(&mut self, hh: u32) -> Result<(), ParseError> {
+        self.set(hh, 9999, |tc| &mut tc.hh)
+    }
+
+    /// Sets the minutes field, at most 59
+    pub fn set_minutes(&mut self, mm: u32) -> Result<(), ParseError> {
+        self.set(mm, 59, |tc| &mut tc.mm)
+    }
+
+    /// Sets the seconds field, at most 59
+    pub fn set_seconds(&mut self, ss: u32) -> Result<(), ParseError> {
+        self.set(ss, 59, |tc| &mut tc.ss)
+    }
+
+    /// Sets the milliseconds field, at most 999
+    pub fn set_millis(&mut self, ttt: u32) -> Result<(), ParseError> {
+        self.set(ttt, 999, |tc| &mut tc.ttt)
+    }
+
+    // Sets a field and renders the string again, keeping its separator
+    // and whether it has an hours field
+    fn set<F>(&mut self, value: u32, max: u32, field: F) -> Result<(), ParseError>
+    where
+        F: FnOnce(&mut TimeCode) -> &mut u32,
+    {
+        if value > max {
+            return Err(ErrorKind::InvalidTimecode.into());
+        }
+        *field(self) = value;
+        self.string = if self.hh > 0 || self.string.matches(':').count() == 2 {
+            self.to_string()
+        } else {
+            let separator = if self.string.contains(',') { ',' } else { '.' };
+            format!("{:02}:{:02}{}{:03}", self.mm, self.ss, separator, self.ttt)
+        };
+        Ok(())
+    }
+
+    /// Converts a tick count of a clock running at `ticks_per_second`,
+    /// such as an MP4 timescale, rounding to the nearest millisecond
+    ///
+    /// Same as converting with a [`TimeBase`], which is cheaper when
+    /// converting many tick counts of the same clock.
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCode;
+    ///
+    /// let tc = TimeCode::from_ticks(6_733_350, 90_000).unwrap();
+    /// assert_eq!(tc.as_str(), "00:01:14.815");
+    /// assert!(TimeCode::from_ticks(1, 0).is_err());
+    /// ```
+    pub fn from_ticks(ticks: u64, ticks_per_second: u64) -> Result<TimeCode, ParseError> {
+        TimeBase::new(ticks_per_second)?.to_timecode(ticks)
+    }
+
+    /// Converts a number of seconds, such as from JSON, rounding to the
+    /// nearest millisecond
+    ///
+    /// Rounding (rather than truncating) keeps values like `74.815`,
+    /// which floats store as slightly less, on the millisecond written.
+    /// Negative, infinite and NaN seconds are rejected.
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCode;
+    ///
+    /// assert_eq!(TimeCode::from_seconds_f64(74.815).unwrap().as_str(), "00:01:14.815");
+    /// assert_eq!(TimeCode::from_seconds_f64(0.0004).unwrap().total_millis(), 0);
+    /// assert!(TimeCode::from_seconds_f64(-1.0).is_err());
+    /// ```
+    pub fn from_seconds_f64(seconds: f64) -> Result<TimeCode, ParseError> {
+        let ms = (seconds * 1000.0).round();
+        // as casts saturate, so check the range first
+        if !(0.0..=u64::MAX as f64).contains(&ms) {
+            return Err(ErrorKind::InvalidTimecode.into());
+        }
+        TimeCode::from_millis(ms as u64)
+    }
+
     /// Converts TimeCode to seconds
     ///
     /// Converts hh and mm to seconds, sums them with ss field.
     /// milliseconds are skipped, rounding the number seconds down to the nearest
-    /// second.
+    /// second, see [`TimeCode::total_millis`] to keep them.
     ///
     /// ```
     /// use title_parser::timecode::{TimeCodeTrait};
@@ -82,6 +277,317 @@ impl TimeCode {
     pub fn to_seconds(&self) -> u32 {
         (self.hh * 60 * 60) + (self.mm * 60) + (self.ss)
     }
+
+    /// Converts TimeCode to milliseconds, keeping the precision
+    /// [`TimeCode::to_seconds`] drops
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCodeTrait;
+    /// let tc = "01:01:14.815".to_timecode().unwrap();
+    /// assert_eq!(tc.total_millis(), 3_674_815);
+    /// ```
+    pub fn total_millis(&self) -> u64 {
+        u64::from(self.to_seconds()) * 1000 + u64::from(self.ttt)
+    }
+
+    /// Builds a timecode from its fields, with a canonical
+    /// `hh:mm:ss.ttt` string
+    ///
+    /// Hours go up to 9999, minutes and seconds up to 59 and
+    /// milliseconds up to 999, anything larger is an
+    /// `InvalidTimecode` error.
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCode;
+    ///
+    /// let tc = TimeCode::new(1, 2, 3, 4).unwrap();
+    /// assert_eq!(tc.as_str(), "01:02:03.004");
+    /// assert!(TimeCode::new(0, 60, 0, 0).is_err());
+    /// ```
+    pub fn new(hh: u32, mm: u32, ss: u32, ttt: u32) -> Result<TimeCode, ParseError> {
+        if hh > 9999 || mm > 59 || ss > 59 || ttt > 999 {
+            return Err(ErrorKind::InvalidTimecode.into());
+        }
+        Ok(TimeCode {
+            string: format!("{:02}:{:02}:{:02}.{:03}", hh, mm, ss, ttt),
+            hh,
+            mm,
+            ss,
+            ttt,
+        })
+    }
+
+    /// Builds a timecode from a number of milliseconds, with a
+    /// canonical `hh:mm:ss.ttt` string
+    ///
+    /// Fails past the largest timecode, 9999:59:59.999.
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCode;
+    ///
+    /// let tc = TimeCode::from_millis(3_674_815).unwrap();
+    /// assert_eq!(tc.as_str(), "01:01:14.815");
+    /// assert_eq!(tc, TimeCode::new(1, 1, 14, 815).unwrap());
+    /// ```
+    pub fn from_millis(ms: u64) -> Result<TimeCode, ParseError> {
+        let hh = ms / 3_600_000;
+        if hh > 9999 {
+            return Err(ErrorKind::InvalidTimecode.into());
+        }
+        TimeCode::new(
+            hh as u32,
+            (ms / 60_000 % 60) as u32,
+            (ms / 1000 % 60) as u32,
+            (ms % 1000) as u32,
+        )
+    }
+}
+
+impl TimeCode {
+    /// Moves the timecode later by `offset`, or returns `None` past the
+    /// largest timecode, 9999:59:59.999
+    ///
+    /// Anything below a millisecond in `offset` is dropped. The string
+    /// is rendered again with all fields, keeping its separator.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use title_parser::timecode::TimeCodeTrait;
+    ///
+    /// let tc = "01:14,815".to_timecode().unwrap();
+    /// let later = tc.checked_add(Duration::from_millis(1_500)).unwrap();
+    /// assert_eq!(later.as_str(), "00:01:16,315");
+    /// ```
+    pub fn checked_add(&self, offset: Duration) -> Option<TimeCode> {
+        let offset = u64::try_from(offset.as_millis()).ok()?;
+        self.with_millis(self.total_millis().checked_add(offset)?)
+    }
+
+    /// Moves the timecode earlier by `offset`, or returns `None` before
+    /// zero, see [`TimeCode::checked_add`]
+    pub fn checked_sub(&self, offset: Duration) -> Option<TimeCode> {
+        let offset = u64::try_from(offset.as_millis()).ok()?;
+        self.with_millis(self.total_millis().checked_sub(offset)?)
+    }
+
+    /// Moves the timecode earlier by `offset`, stopping at zero, see
+    /// [`TimeCode::checked_add`]
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use title_parser::timecode::TimeCodeTrait;
+    ///
+    /// let tc = "00:00:01.000".to_timecode().unwrap();
+    /// assert_eq!(tc.saturating_sub(Duration::from_secs(5)).as_str(), "00:00:00.000");
+    /// ```
+    pub fn saturating_sub(&self, offset: Duration) -> TimeCode {
+        self.checked_sub(offset)
+            .unwrap_or_else(|| self.with_millis(0).expect("zero is a valid timecode"))
+    }
+
+    // Builds a TimeCode for `ms` written with the same separator
+    pub(crate) fn with_millis(&self, ms: u64) -> Option<TimeCode> {
+        let mut tc = TimeCode::from_millis(ms).ok()?;
+        if self.string.contains(',') {
+            tc.string = tc.string.replace('.', ",");
+        }
+        Some(tc)
+    }
+}
+
+/// Parses a strict SRT or WebVTT timecode, see [`TimeCodeTrait`]
+///
+/// ```
+/// use title_parser::timecode::TimeCode;
+///
+/// let tc: TimeCode = "01:14,815".parse().unwrap();
+/// assert_eq!(tc.to_string(), "00:01:14,815");
+/// assert!("1:14.815".parse::<TimeCode>().is_err());
+/// ```
+impl FromStr for TimeCode {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<TimeCode, ParseError> {
+        input.to_timecode()
+    }
+}
+
+/// Renders the canonical `hh:mm:ss` form followed by the separator the
+/// timecode was written with: `,` for SRT, `.` for WebVTT
+///
+/// Timecodes built from numbers use `.`. [`TimeCode::as_str`] gives
+/// the timecode exactly as written instead.
+impl fmt::Display for TimeCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.string.contains(',') { ',' } else { '.' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:03}",
+            self.hh, self.mm, self.ss, separator, self.ttt
+        )
+    }
+}
+
+/// Shifts a timecode later, see [`TimeCode::checked_add`]
+///
+/// Panics past the largest timecode.
+impl Add<Duration> for TimeCode {
+    type Output = TimeCode;
+
+    fn add(self, offset: Duration) -> TimeCode {
+        &self + offset
+    }
+}
+
+impl Add<Duration> for &TimeCode {
+    type Output = TimeCode;
+
+    fn add(self, offset: Duration) -> TimeCode {
+        self.checked_add(offset)
+            .expect("overflow when adding duration to timecode")
+    }
+}
+
+/// Shifts a timecode earlier, see [`TimeCode::checked_sub`]
+///
+/// Panics before zero.
+///
+/// ```
+/// use std::time::Duration;
+/// use title_parser::timecode::TimeCodeTrait;
+///
+/// let tc = "00:01:14.815".to_timecode().unwrap();
+/// assert_eq!((tc - Duration::from_millis(815)).as_str(), "00:01:14.000");
+/// ```
+impl Sub<Duration> for TimeCode {
+    type Output = TimeCode;
+
+    fn sub(self, offset: Duration) -> TimeCode {
+        &self - offset
+    }
+}
+
+impl Sub<Duration> for &TimeCode {
+    type Output = TimeCode;
+
+    fn sub(self, offset: Duration) -> TimeCode {
+        self.checked_sub(offset)
+            .expect("overflow when subtracting duration from timecode")
+    }
+}
+
+/// ```
+/// use std::time::Duration;
+/// use title_parser::timecode::TimeCodeTrait;
+///
+/// let tc = "00:01:14.815".to_timecode().unwrap();
+/// assert_eq!(Duration::from(tc), Duration::from_millis(74_815));
+/// ```
+impl From<TimeCode> for Duration {
+    fn from(tc: TimeCode) -> Duration {
+        Duration::from(&tc)
+    }
+}
+
+impl From<&TimeCode> for Duration {
+    fn from(tc: &TimeCode) -> Duration {
+        Duration::from_millis(tc.total_millis())
+    }
+}
+
+/// Converts a duration to a TimeCode, dropping anything below a
+/// millisecond
+///
+/// Fails for durations of 10000 hours or more, which no timecode can
+/// hold.
+///
+/// ```
+/// use std::time::Duration;
+/// use title_parser::timecode::TimeCode;
+///
+/// let tc = TimeCode::try_from(Duration::from_micros(74_815_900)).unwrap();
+/// assert_eq!(tc.as_str(), "00:01:14.815");
+/// assert!(TimeCode::try_from(Duration::from_secs(36_000_000)).is_err());
+/// ```
+impl TryFrom<Duration> for TimeCode {
+    type Error = ParseError;
+
+    fn try_from(duration: Duration) -> Result<TimeCode, ParseError> {
+        let ms = u64::try_from(duration.as_millis()).map_err(|_| ErrorKind::InvalidTimecode)?;
+        TimeCode::from_millis(ms)
+    }
+}
+
+/// A TimeBase describes a clock counting in ticks at a fixed rate, such
+/// as MP4 track timescales, the 90kHz MPEG-TS clock or TTML tick rates
+///
+/// Converting absolute tick values (rather than accumulating converted
+/// durations) keeps long files free of cumulative rounding errors.
+///
+/// ```
+/// use title_parser::timecode::{TimeBase, TimeCodeTrait};
+///
+/// let tc = TimeBase::MPEG_TS.to_timecode(6_733_350).unwrap();
+/// assert_eq!(tc.as_str(), "00:01:14.815");
+///
+/// let tc = "00:01:14.815".to_timecode().unwrap();
+/// assert_eq!(TimeBase::MPEG_TS.to_ticks(&tc), 6_733_350);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBase {
+    rate: u64,
+}
+
+impl TimeBase {
+    /// The 90kHz clock used by MPEG-TS presentation timestamps
+    pub const MPEG_TS: TimeBase = TimeBase { rate: 90_000 };
+
+    /// Creates a time base counting `rate` ticks per second
+    ///
+    /// ```
+    /// use title_parser::ErrorKind;
+    /// use title_parser::timecode::TimeBase;
+    ///
+    /// assert!(TimeBase::new(48_000).is_ok());
+    /// assert_eq!(TimeBase::new(0).unwrap_err().kind, ErrorKind::InvalidTimeBase);
+    /// ```
+    pub fn new(rate: u64) -> Result<TimeBase, ParseError> {
+        if rate == 0 {
+            return Err(ErrorKind::InvalidTimeBase.into());
+        }
+        Ok(TimeBase { rate })
+    }
+
+    /// Number of ticks per second
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Converts a tick count to a TimeCode, rounding to the nearest
+    /// millisecond
+    pub fn to_timecode(&self, ticks: u64) -> Result<TimeCode, ParseError> {
+        let rate = u128::from(self.rate);
+        let ms = (u128::from(ticks) * 1000 + rate / 2) / rate;
+        let ms = u64::try_from(ms).map_err(|_| ErrorKind::InvalidTimecode)?;
+        TimeCode::from_millis(ms)
+    }
+
+    /// Converts a TimeCode to a tick count, rounding to the nearest tick
+    /// (halves round up), or `u64::MAX` for counts too large to hold
+    ///
+    /// ```
+    /// use title_parser::timecode::{TimeBase, TimeCode};
+    ///
+    /// // 5ms at 44.1kHz is 220.5 ticks
+    /// let base = TimeBase::new(44_100).unwrap();
+    /// assert_eq!(base.to_ticks(&TimeCode::from_millis(5).unwrap()), 221);
+    /// ```
+    pub fn to_ticks(&self, tc: &TimeCode) -> u64 {
+        let exact = u128::from(tc.total_millis()) * u128::from(self.rate);
+        // add half a tick so the division rounds rather than truncates
+        let ticks = (exact + 500) / 1000;
+        u64::try_from(ticks).unwrap_or(u64::MAX)
+    }
 }
 
 #[cfg(test)]
@@ -89,7 +595,19 @@ mod tests {
     use super::*;
 
     #[test]
-    fn timecode_from_str() -> Result<(), String> {
+    fn lenient_timecodes() -> Result<(), ParseError> {
+        assert_eq!(parse_lenient("00:00:01,5")?.total_millis(), 1_500);
+        assert_eq!(parse_lenient("1:02:03.04")?.total_millis(), 3_723_040);
+        assert_eq!(
+            parse_lenient("00:01:14.815")?,
+            "00:01:14.815".to_timecode()?
+        );
+        assert!(parse_lenient("00:61:14.815").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn timecode_from_str() -> Result<(), ParseError> {
         let tc_string = "01:02:03.004";
         let expected = TimeCode {
             string: tc_string.to_string(),
@@ -103,7 +621,7 @@ mod tests {
     }
 
     #[test]
-    fn timecode_from_str_no_hours() -> Result<(), String> {
+    fn timecode_from_str_no_hours() -> Result<(), ParseError> {
         let tc_string = "02:03.004";
         let expected = TimeCode {
             string: tc_string.to_string(),
@@ -117,25 +635,124 @@ mod tests {
     }
 
     #[test]
-    fn timecode_from_invalid_str() -> Result<(), String> {
+    fn shifting_keeps_fields_and_string_together() -> Result<(), ParseError> {
+        let tc = "9999:59:59.000".to_timecode()?;
+        assert!(tc.checked_add(Duration::from_secs(1)).is_none());
+        let earlier = tc - Duration::from_millis(3_600_000 * 9999 + 1);
+        assert_eq!(earlier.as_str(), "00:59:58.999");
+        assert_eq!(
+            (earlier.minutes(), earlier.seconds(), earlier.millis()),
+            (59, 58, 999)
+        );
+        assert_eq!(earlier.checked_sub(Duration::from_secs(3600)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ordering_agrees_with_equality() -> Result<(), ParseError> {
+        let a = "01:14.815".to_timecode()?;
+        let b = "00:01:14,815".to_timecode()?;
+        assert_ne!(a, b);
+        assert_ne!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+        let later = "00:01:14.816".to_timecode()?;
+        assert!(a < later && b < later);
+        let keys: std::collections::HashSet<TimeCode> = [a.clone(), a, b].into();
+        assert_eq!(keys.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn timecode_from_invalid_str() -> Result<(), ParseError> {
         let tc_string = "01:02:03";
-        assert_eq!(tc_string.to_timecode(), Err("invalid timecode".to_string()));
+        assert_eq!(
+            tc_string.to_timecode(),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
         let tc_string = "0a:02:03.001";
-        assert_eq!(tc_string.to_timecode(), Err("invalid timecode".to_string()));
+        assert_eq!(
+            tc_string.to_timecode(),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
+        // Arabic-Indic digits are digits, but not ones `u32` parses
+        let tc_string = "00:00:0\u{663}.000";
+        assert_eq!(
+            tc_string.to_timecode(),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
+        assert_eq!(
+            parse_lenient(tc_string),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
         Ok(())
     }
 
     #[test]
-    fn timecode_with_invalid_values() -> Result<(), String> {
+    fn timecode_with_invalid_values() -> Result<(), ParseError> {
         let tc_string = "01:02:60.004";
-        assert_eq!(tc_string.to_timecode(), Err("invalid timecode".to_string()));
+        assert_eq!(
+            tc_string.to_timecode(),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
         let tc_string = "01:60:03.004";
-        assert_eq!(tc_string.to_timecode(), Err("invalid timecode".to_string()));
+        assert_eq!(
+            tc_string.to_timecode(),
+            Err(ErrorKind::InvalidTimecode.into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn constructors_validate_fields() -> Result<(), ParseError> {
+        assert_eq!(TimeCode::new(9999, 59, 59, 999)?.as_str(), "9999:59:59.999");
+        assert!(TimeCode::new(10_000, 0, 0, 0).is_err());
+        assert!(TimeCode::new(0, 0, 60, 0).is_err());
+        assert!(TimeCode::new(0, 0, 0, 1000).is_err());
+        let tc = TimeCode::new(0, 1, 14, 815)?;
+        assert_eq!(TimeCode::from_millis(tc.total_millis())?, tc);
+        assert!(TimeCode::from_millis(10_000 * 3_600_000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn timebase_round_trip() -> Result<(), ParseError> {
+        for rate in [1000, 25, 48_000, 90_000, 10_000_000] {
+            let base = TimeBase::new(rate)?;
+            let tc = "01:02:03.004".to_timecode()?;
+            let back = base.to_timecode(base.to_ticks(&tc))?;
+            if rate >= 1000 {
+                assert_eq!(back.string, "01:02:03.004");
+            }
+            assert_eq!(back.to_seconds(), 3723);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn timebase_rounds_inexact_ticks() -> Result<(), ParseError> {
+        let base = TimeBase::new(44_100)?;
+        // 44.1, 132.3 and 220.5 ticks
+        let ticks: Vec<u64> = [1, 3, 5]
+            .iter()
+            .map(|&ms| TimeCode::from_millis(ms).map(|tc| base.to_ticks(&tc)))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(ticks, [44, 132, 221]);
+        let huge = TimeBase::new(u64::MAX)?;
+        assert_eq!(huge.to_ticks(&TimeCode::new(9999, 0, 0, 0)?), u64::MAX);
+        Ok(())
+    }
+
+    #[test]
+    fn timebase_no_cumulative_drift() -> Result<(), ParseError> {
+        // 1001 ticks at 30000 per second is one NTSC frame (33.366ms)
+        let base = TimeBase::new(30_000)?;
+        let tc = base.to_timecode(1001 * 108_000)?;
+        assert_eq!(tc.string, "01:00:03.600");
         Ok(())
     }
 
     #[test]
-    fn timecode_to_seconds() -> Result<(), String> {
+    fn timecode_to_seconds() -> Result<(), ParseError> {
         let tc_string = "01:02:03.004";
         assert_eq!(tc_string.to_timecode()?.to_seconds(), 3723);
         Ok(())
@@ -0,0 +1,1465 @@
+//! parses entire SRT / WebVTT documents into a `Track`
+
+use crate::encoding::decode;
+use crate::style::StyleSheet;
+use crate::timecode::TimeCode;
+use crate::warning::{self, Warning, WarningKind};
+use crate::{
+    exceeds, is_timing_line, BorrowedCue, Cue, CueTrait, Error, ErrorKind, ParseError, ParseMode,
+    ParseOptions, Span,
+};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Subtitle formats understood by the parser
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// SubRip text, `.srt`
+    Srt,
+    /// Web Video Text Tracks, `.vtt`
+    WebVtt,
+}
+
+/// Guesses the format of a document from its content
+///
+/// Documents starting with a `WEBVTT` signature, after any byte order
+/// mark, are WebVTT. Everything else is taken to be SRT, the format with
+/// the least to recognise it by.
+///
+/// ```
+/// use title_parser::track::{detect_format, Format};
+///
+/// assert_eq!(detect_format("\u{feff}WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n"), Format::WebVtt);
+/// assert_eq!(detect_format("1\n00:00:01,000 --> 00:00:02,000\nHi\n"), Format::Srt);
+/// assert_eq!(detect_format("WEBVTTX\n"), Format::Srt);
+/// ```
+pub fn detect_format(input: &str) -> Format {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    match vtt_signature(input) {
+        Some(_) => Format::WebVtt,
+        None => Format::Srt,
+    }
+}
+
+/// A block left out by [`Track::parse_recovering`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedBlock {
+    /// (0-based) position of the block among the blank-line separated
+    /// blocks of the document, header included
+    pub index: usize,
+    /// the block as written
+    pub text: String,
+    /// why the block was skipped
+    pub error: ParseError,
+}
+
+/// Options for [`Track::clamp_to_with`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClampOptions {
+    /// remove cues that lie entirely outside the window instead of
+    /// collapsing them onto its edges
+    pub drop_outside: bool,
+}
+
+/// A Track represents an entire SRT / WebVTT document:
+///
+/// ```vtt
+/// WEBVTT - This file has cues.
+///
+/// 14
+/// 00:01:14.815 --> 00:01:18.114
+/// - What?
+/// - Where are we now?
+///
+/// 15
+/// 00:01:18.171 --> 00:01:20.991
+/// - This is big bat country.
+/// ```
+///
+#[derive(Debug)]
+pub struct Track {
+    /// format the document was written in
+    pub format: Format,
+    /// text following the `WEBVTT` signature, if any
+    pub description: Option<String>,
+    /// remaining lines of the WebVTT header block, such as `Kind: captions`
+    pub metadata: Vec<String>,
+    /// styles defined by the document, such as WebVTT `STYLE` blocks
+    pub styles: StyleSheet,
+    /// cues in the order they appear in the document
+    pub cues: Vec<Cue>,
+    /// name of the encoding the input was decoded from, when parsed
+    /// from bytes
+    pub encoding: Option<&'static str>,
+    /// how sure detection was about [`Track::encoding`]
+    pub encoding_confidence: Option<Confidence>,
+}
+
+/// How sure encoding detection was about its choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// a guess among single byte encodings that decode almost anything
+    Low,
+    /// the content clearly points at the encoding, or the caller hinted
+    /// at it
+    High,
+    /// a byte order mark or the caller said so, or the input is plain
+    /// ASCII which reads the same in any candidate encoding
+    Certain,
+}
+
+impl Track {
+    /// Attempts to parse an entire SRT or WebVTT document
+    ///
+    /// WebVTT documents are recognised by their `WEBVTT` signature,
+    /// anything else is parsed as SRT. `NOTE` blocks in WebVTT documents
+    /// are attached to the following cue, `STYLE` blocks are read into
+    /// [`Track::styles`] and `REGION` blocks are skipped.
+    ///
+    /// ```
+    /// use title_parser::track::{Format, Track};
+    ///
+    /// let text = "WEBVTT - This file has cues.\n\n14\n00:01:14.815 --> 00:01:18.114\n- What?\n- Where are we now?\n\n15\n00:01:18.171 --> 00:01:20.991\n- This is big bat country.\n";
+    /// let track = Track::parse(text).unwrap();
+    /// assert_eq!(track.format, Format::WebVtt);
+    /// assert_eq!(track.description.as_deref(), Some("- This file has cues."));
+    /// assert_eq!(track.cues.len(), 2);
+    /// assert_eq!(track.cues[1].text, "This is big bat country.");
+    /// ```
+    pub fn parse(input: &str) -> Result<Track, ParseError> {
+        Track::parse_with(input, &ParseOptions::default())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document using the
+    /// given options
+    ///
+    /// Errors point at the offending part of `input`.
+    ///
+    /// ```
+    /// use title_parser::ErrorKind;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 -> 00:00:04,000\nWorld\n";
+    /// let err = Track::parse(text).unwrap_err();
+    /// assert_eq!(err.kind, ErrorKind::MissingArrow);
+    /// assert_eq!((err.span.offset, err.span.line, err.span.column), (41, 6, 1));
+    /// ```
+    pub fn parse_with(input: &str, options: &ParseOptions) -> Result<Track, ParseError> {
+        Track::parse_with_warnings(input, options).map(|(track, _)| track)
+    }
+
+    /// Detects the format of a document with [`detect_format`] and parses
+    /// it as that format, see [`Track::parse_as`]
+    ///
+    /// ```
+    /// use title_parser::ParseOptions;
+    /// use title_parser::track::{Format, Track};
+    ///
+    /// let text = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHello\n";
+    /// let track = Track::parse_auto(text, &ParseOptions::default()).unwrap();
+    /// assert_eq!(track.format, Format::WebVtt);
+    /// ```
+    pub fn parse_auto(input: &str, options: &ParseOptions) -> Result<Track, ParseError> {
+        Track::parse_as(input, detect_format(input), options)
+    }
+
+    /// Attempts to parse a document known to be in the given format
+    ///
+    /// WebVTT documents must start with a `WEBVTT` signature, while a
+    /// signature at the top of an SRT document is an invalid cue.
+    ///
+    /// ```
+    /// use title_parser::{ErrorKind, ParseOptions};
+    /// use title_parser::track::{Format, Track};
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+    /// let options = ParseOptions::default();
+    /// assert_eq!(Track::parse_as(text, Format::Srt, &options).unwrap().cues.len(), 1);
+    /// let err = Track::parse_as(text, Format::WebVtt, &options).unwrap_err();
+    /// assert_eq!(err.kind, ErrorKind::MissingSignature);
+    /// ```
+    pub fn parse_as(
+        input: &str,
+        format: Format,
+        options: &ParseOptions,
+    ) -> Result<Track, ParseError> {
+        if exceeds(input.len(), options.limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(options.limits.max_input_bytes.unwrap()).into());
+        }
+        let mut builder = TrackBuilder::new(options);
+        builder.expected = Some(format);
+        for (at, block) in Blocks::new(input) {
+            builder.push_block(at, block)?;
+        }
+        if builder.first && format == Format::WebVtt {
+            return Err(ErrorKind::MissingSignature.into());
+        }
+        Ok(builder.finish())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document, also returning
+    /// the non-fatal problems found along the way
+    ///
+    /// ```
+    /// use title_parser::ParseOptions;
+    /// use title_parser::track::Track;
+    /// use title_parser::warning::WarningKind;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:03,000\nHello\n\n2\n00:00:02,000 --> 00:00:04,000\nWorld\n";
+    /// let (track, warnings) = Track::parse_with_warnings(text, &ParseOptions::default()).unwrap();
+    /// assert_eq!(track.cues.len(), 2);
+    /// assert_eq!(warnings[0].line, 5);
+    /// assert_eq!(warnings[0].kind, WarningKind::Overlap);
+    /// ```
+    pub fn parse_with_warnings(
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<(Track, Vec<Warning>), ParseError> {
+        if exceeds(input.len(), options.limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(options.limits.max_input_bytes.unwrap()).into());
+        }
+        let mut builder = TrackBuilder::new(options);
+        for (at, block) in Blocks::new(input) {
+            builder.push_block(at, block)?;
+        }
+        Ok(builder.finish_with_warnings())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document, skipping
+    /// blocks that fail to parse instead of giving up on the document
+    ///
+    /// Returns the track built from the good blocks along with every
+    /// skipped one. Errors about the document as a whole, such as
+    /// exceeding [`crate::Limits`], still fail the parse.
+    ///
+    /// ```
+    /// use title_parser::{ErrorKind, ParseOptions};
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 -> 00:00:04,000\nbroken\n\n3\n00:00:05,000 --> 00:00:06,000\nWorld\n";
+    /// let (track, skipped) = Track::parse_recovering(text, &ParseOptions::default()).unwrap();
+    /// assert_eq!(track.cues.len(), 2);
+    /// assert_eq!(skipped[0].index, 1);
+    /// assert!(skipped[0].text.ends_with("broken"));
+    /// assert_eq!(skipped[0].error.kind, ErrorKind::MissingArrow);
+    /// ```
+    pub fn parse_recovering(
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<(Track, Vec<SkippedBlock>), ParseError> {
+        if exceeds(input.len(), options.limits.max_input_bytes) {
+            return Err(ErrorKind::InputTooLarge(options.limits.max_input_bytes.unwrap()).into());
+        }
+        let mut builder = TrackBuilder::new(options);
+        let mut skipped = Vec::new();
+        for (index, (at, block)) in Blocks::new(input).enumerate() {
+            match builder.push_block(at, block) {
+                Err(e) if matches!(e.kind, ErrorKind::TooManyCues(_)) => return Err(e),
+                Err(error) => skipped.push(SkippedBlock {
+                    index,
+                    text: block.to_string(),
+                    error,
+                }),
+                Ok(()) => {}
+            }
+        }
+        Ok((builder.finish(), skipped))
+    }
+
+    /// Attempts to parse an SRT or WebVTT document from raw bytes
+    ///
+    /// UTF-8 and UTF-16 (little or big endian) input is accepted, with or
+    /// without a byte order mark. With the `encoding_rs` feature, other
+    /// input is decoded as Shift_JIS if that yields Japanese text and as
+    /// windows-1252 otherwise. The detected encoding is stored in
+    /// [`Track::encoding`], and how sure detection was about it in
+    /// [`Track::encoding_confidence`].
+    ///
+    /// Parse errors come as [`Error::Parse`] with their [`Span`]. For
+    /// UTF-8 input its offset is into `bytes`, for other encodings into
+    /// the decoded text, while lines and columns hold for both.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let mut bytes = vec![0xFF, 0xFE];
+    /// for unit in "1\n00:00:12,584 --> 00:00:14,876\nガンダム\n".encode_utf16() {
+    ///     bytes.extend_from_slice(&unit.to_le_bytes());
+    /// }
+    /// let track = Track::from_bytes(&bytes).unwrap();
+    /// assert_eq!(track.cues[0].text, "ガンダム");
+    /// assert_eq!(track.encoding, Some("UTF-16LE"));
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Track, Error> {
+        Track::from_bytes_with(bytes, &ParseOptions::default())
+    }
+
+    /// Attempts to parse an SRT or WebVTT document from raw bytes using
+    /// the given options
+    ///
+    /// [`ParseOptions::encoding`] can hint at or force the encoding.
+    ///
+    /// ```
+    /// use title_parser::{Encoding, ParseOptions};
+    /// use title_parser::track::{Confidence, Track};
+    ///
+    /// let options = ParseOptions {
+    ///     encoding: Encoding::Override("UTF-8".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let track = Track::from_bytes_with(b"1\n00:00:01,000 --> 00:00:02,000\nHi\n", &options).unwrap();
+    /// assert_eq!(track.encoding_confidence, Some(Confidence::Certain));
+    /// ```
+    pub fn from_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<Track, Error> {
+        let decoded = decode(bytes, &options.encoding).map_err(Error::Encoding)?;
+        let mut track = Track::parse_with(&decoded.text, options)
+            .map_err(|e| in_bytes(e, bytes, &decoded.text))?;
+        track.encoding = Some(decoded.encoding);
+        track.encoding_confidence = Some(decoded.confidence);
+        Ok(track)
+    }
+
+    /// Attempts to read and parse an SRT or WebVTT file
+    ///
+    /// The format is detected from the content, as with [`Track::parse`],
+    /// except that a `.vtt` file without a `WEBVTT` signature is
+    /// rejected. Errors are [`Error::File`]s carrying the path, and
+    /// parse errors within them also carry their line and column.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let track = Track::from_path("src/example.vtt").unwrap();
+    /// assert_eq!(track.cues.len(), 3);
+    ///
+    /// let err = Track::from_path("src/missing.srt").err().unwrap();
+    /// assert!(err.to_string().starts_with("src/missing.srt: "));
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Track, Error> {
+        Track::from_path_with(path, &ParseOptions::default())
+    }
+
+    /// Attempts to read and parse an SRT or WebVTT file using the given
+    /// options
+    pub fn from_path_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Track, Error> {
+        read_path(path.as_ref(), options, |input| {
+            Track::parse_with(input, options)
+        })
+    }
+
+    /// Computes a hash of the track's content that is stable across
+    /// formats, encodings, crate versions and platforms
+    ///
+    /// Only cue timing (at millisecond precision) and text are hashed.
+    /// Text is normalized by trimming every line and collapsing runs of
+    /// whitespace, so the same subtitles written as SRT or WebVTT, or
+    /// with different line endings, hash to the same value.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let srt = Track::parse("1\r\n00:01:14,815 --> 00:01:18,114\r\n- What?\r\n").unwrap();
+    /// let vtt = Track::parse("WEBVTT\n\n00:01:14.815 --> 00:01:18.114\nWhat?  \n").unwrap();
+    /// assert_eq!(srt.content_hash(), vtt.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        // 64-bit FNV-1a, chosen over std's hashers whose output is not
+        // guaranteed to stay the same between releases
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        for cue in &self.cues {
+            feed(&cue.start.total_millis().to_le_bytes());
+            feed(&cue.end.total_millis().to_le_bytes());
+            for (i, line) in cue.text.lines().enumerate() {
+                if i > 0 {
+                    feed(b"\n");
+                }
+                let words: Vec<&str> = line.split_whitespace().collect();
+                feed(words.join(" ").as_bytes());
+            }
+            feed(&[0]);
+        }
+        hash
+    }
+
+    /// Limits the track to the window between `start` and `end`, such as
+    /// when re-publishing part of a live caption feed
+    ///
+    /// Cues crossing an edge of the window are truncated to it, cues
+    /// entirely outside it are collapsed onto the nearest edge. See
+    /// [`Track::clamp_to_with`] to drop those instead. Moved timecodes
+    /// keep their own separator.
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCodeTrait;
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:03,000\nHello\n\n2\n00:00:04,000 --> 00:00:05,000\nWorld\n";
+    /// let mut track = Track::parse(text).unwrap();
+    /// let (start, end) = ("00:00:02.000".to_timecode().unwrap(), "00:00:03.500".to_timecode().unwrap());
+    /// track.clamp_to(&start, &end).unwrap();
+    /// assert_eq!(track.cues[0].start.as_str(), "00:00:02,000");
+    /// assert_eq!(track.cues[0].end.as_str(), "00:00:03,000");
+    /// assert_eq!(track.cues[1].start.as_str(), "00:00:03,500");
+    /// ```
+    pub fn clamp_to(&mut self, start: &TimeCode, end: &TimeCode) -> Result<(), Error> {
+        self.clamp_to_with(start, end, &ClampOptions::default())
+    }
+
+    /// Limits the track to the window between `start` and `end` using the
+    /// given options
+    ///
+    /// ```
+    /// use title_parser::timecode::TimeCodeTrait;
+    /// use title_parser::track::{ClampOptions, Track};
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:03,000\nHello\n\n2\n00:00:04,000 --> 00:00:05,000\nWorld\n";
+    /// let mut track = Track::parse(text).unwrap();
+    /// let (start, end) = ("00:00:02.000".to_timecode().unwrap(), "00:00:03.500".to_timecode().unwrap());
+    /// let options = ClampOptions { drop_outside: true };
+    /// track.clamp_to_with(&start, &end, &options).unwrap();
+    /// assert_eq!(track.cues.len(), 1);
+    /// ```
+    pub fn clamp_to_with(
+        &mut self,
+        start: &TimeCode,
+        end: &TimeCode,
+        options: &ClampOptions,
+    ) -> Result<(), Error> {
+        let (from, to) = (start.total_millis(), end.total_millis());
+        if from > to {
+            return Err(Error::InvalidWindow);
+        }
+        if options.drop_outside {
+            self.cues
+                .retain(|cue| cue.end.total_millis() > from && cue.start.total_millis() < to);
+        }
+        for cue in &mut self.cues {
+            for timecode in [&mut cue.start, &mut cue.end] {
+                let ms = timecode.total_millis();
+                if ms < from || ms > to {
+                    *timecode = timecode
+                        .with_millis(ms.clamp(from, to))
+                        .ok_or(ErrorKind::InvalidTimecode)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document from a reader
+    ///
+    /// The input is read line by line, so cue blocks may be split
+    /// across any number of reads. The input must be UTF-8, use
+    /// [`Track::from_bytes`] for UTF-16 sources.
+    ///
+    /// ```
+    /// use std::io::{BufReader, Read};
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "WEBVTT\n\n00:01:14.815 --> 00:01:18.114\n- What?\n";
+    /// // a tiny buffer forces the cue block to arrive in pieces
+    /// let reader = BufReader::with_capacity(4, text.as_bytes());
+    /// let track = Track::from_reader(reader).unwrap();
+    /// assert_eq!(track.cues[0].text, "What?");
+    /// ```
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Track, Error> {
+        Track::from_reader_with(reader, &ParseOptions::default())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document from a reader
+    /// using the given options
+    pub fn from_reader_with<R: BufRead>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<Track, Error> {
+        let mut builder = TrackBuilder::new(options);
+        let mut lines = LineBlocks::new(options.limits.max_input_bytes);
+        let mut buf = String::new();
+        let mut total = 0;
+        loop {
+            buf.clear();
+            let read = reader.read_line(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            total += read;
+            if exceeds(total, options.limits.max_input_bytes) {
+                let max = options.limits.max_input_bytes.unwrap();
+                return Err(ErrorKind::InputTooLarge(max).into());
+            }
+            if let Some((at, block)) = lines.push_line(&buf)? {
+                builder.push_block(at, &block)?;
+            }
+        }
+        if let Some((at, block)) = lines.finish() {
+            builder.push_block(at, &block)?;
+        }
+        Ok(builder.finish())
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document from an async
+    /// reader, such as an upload or HTTP body
+    ///
+    /// Requires the `tokio` feature.
+    ///
+    /// ```
+    /// use title_parser::track::Track;
+    ///
+    /// let text = "WEBVTT\n\n00:01:14.815 --> 00:01:18.114\n- What?\n";
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let track = runtime.block_on(Track::from_async_reader(text.as_bytes())).unwrap();
+    /// assert_eq!(track.cues[0].text, "What?");
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader<R>(reader: R) -> Result<Track, Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        Track::from_async_reader_with(reader, &ParseOptions::default()).await
+    }
+
+    /// Attempts to parse an entire SRT or WebVTT document from an async
+    /// reader using the given options
+    ///
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader_with<R>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<Track, Error>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        use tokio::io::AsyncBufReadExt;
+
+        let mut builder = TrackBuilder::new(options);
+        let mut lines = LineBlocks::new(options.limits.max_input_bytes);
+        let mut buf = String::new();
+        let mut total = 0;
+        loop {
+            buf.clear();
+            let read = reader.read_line(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+            total += read;
+            if exceeds(total, options.limits.max_input_bytes) {
+                let max = options.limits.max_input_bytes.unwrap();
+                return Err(ErrorKind::InputTooLarge(max).into());
+            }
+            if let Some((at, block)) = lines.push_line(&buf)? {
+                builder.push_block(at, &block)?;
+            }
+        }
+        if let Some((at, block)) = lines.finish() {
+            builder.push_block(at, &block)?;
+        }
+        Ok(builder.finish())
+    }
+}
+
+// Reads and decodes a file, then parses it with `parse`, see
+// `Track::from_path`
+pub(crate) fn read_path<F>(path: &Path, options: &ParseOptions, parse: F) -> Result<Track, Error>
+where
+    F: FnOnce(&str) -> Result<Track, ParseError>,
+{
+    let context = |e: Error| e.in_file(path);
+    let bytes = fs::read(path).map_err(|e| context(e.into()))?;
+    let decoded = decode(&bytes, &options.encoding).map_err(|e| context(Error::Encoding(e)))?;
+    let input = decoded.text;
+    let is_vtt_file = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("vtt"));
+    if is_vtt_file && vtt_signature(&input).is_none() {
+        return Err(context(ErrorKind::MissingSignature.into()));
+    }
+    let mut track = parse(&input).map_err(|e| context(in_bytes(e, &bytes, &input).into()))?;
+    track.encoding = Some(decoded.encoding);
+    track.encoding_confidence = Some(decoded.confidence);
+    Ok(track)
+}
+
+// Moves an error found in decoded text onto the bytes it came from.
+// Only UTF-8 is decoded in place, skipping at most a byte order mark,
+// so errors in other encodings stay where they are in the decoded text.
+fn in_bytes(error: ParseError, bytes: &[u8], text: &str) -> ParseError {
+    let in_place = bytes.as_ptr_range().contains(&text.as_ptr());
+    if !in_place || text.len() == bytes.len() {
+        return error;
+    }
+    let bom = Span {
+        offset: bytes.len() - text.len(),
+        line: 1,
+        column: 2,
+    };
+    ParseError::new(error.kind, error.span.within(bom))
+}
+
+// Assembles a Track from the blocks of a document, in order
+pub(crate) struct TrackBuilder<'o> {
+    track: Track,
+    warnings: Vec<Warning>,
+    // notes waiting for the cue they precede
+    notes: Vec<String>,
+    options: &'o ParseOptions,
+    first: bool,
+    // format the document must be in, or None to detect it
+    expected: Option<Format>,
+}
+
+// Cues of a block along with where each starts and the warnings found
+// without looking at the rest of the track
+pub(crate) type ParsedCues = Vec<Result<(Span, Cue, Vec<WarningKind>), ParseError>>;
+
+// Parses the cues of a block that is neither a header nor a WebVTT
+// NOTE, STYLE or REGION block
+pub(crate) fn parse_cues(at: Span, block: &str, options: &ParseOptions) -> ParsedCues {
+    split_cues(block, options.mode)
+        .into_iter()
+        .map(|(offset, text)| {
+            let cue_at = Span::locate(block, offset).within(at);
+            let cue = normalize_newlines(text)
+                .to_cue_with(options)
+                .map_err(|e| relocate(e, text, cue_at))?;
+            Ok((cue_at, cue, warning::unknown_settings(text)))
+        })
+        .collect()
+}
+
+impl<'o> TrackBuilder<'o> {
+    pub(crate) fn new(options: &'o ParseOptions) -> TrackBuilder<'o> {
+        TrackBuilder {
+            track: Track {
+                format: Format::Srt,
+                description: None,
+                metadata: Vec::new(),
+                styles: StyleSheet::default(),
+                cues: Vec::new(),
+                encoding: None,
+                encoding_confidence: None,
+            },
+            warnings: Vec::new(),
+            notes: Vec::new(),
+            options,
+            first: true,
+            expected: None,
+        }
+    }
+
+    // Adds the block starting at `at` in the document
+    pub(crate) fn push_block(&mut self, at: Span, block: &str) -> Result<(), ParseError> {
+        self.push_parsed(at, block, None)
+    }
+
+    // Adds a block whose cues may already have been parsed by
+    // `parse_cues`, such as on another thread. The first block of a
+    // document is always parsed here, as it may hold a byte order mark.
+    pub(crate) fn push_parsed(
+        &mut self,
+        at: Span,
+        block: &str,
+        parsed: Option<ParsedCues>,
+    ) -> Result<(), ParseError> {
+        let track = &mut self.track;
+        let (mut at, mut block) = (at, block);
+        if std::mem::take(&mut self.first) {
+            (at, block) = strip_bom(at, block);
+            let signature = match self.expected {
+                Some(Format::Srt) => None,
+                _ => vtt_signature(block),
+            };
+            if signature.is_none() && self.expected == Some(Format::WebVtt) {
+                return Err(ParseError::new(ErrorKind::MissingSignature, at));
+            }
+            if let Some(description) = signature {
+                trace!(debug, ?description, "WebVTT header found");
+                track.format = Format::WebVtt;
+                track.description = description.map(|d| d.to_string());
+                track.metadata = block.lines().skip(1).map(|l| l.to_string()).collect();
+                return Ok(());
+            }
+        }
+        if track.format == Format::WebVtt && is_vtt_extra_block(block) {
+            trace!(trace, line = at.line, "NOTE, STYLE or REGION block");
+            self.notes.extend(note_text(block));
+            for style in StyleSheet::from_vtt(style_css(block)).styles {
+                track.styles.insert(style);
+            }
+            return Ok(());
+        }
+        let parsed = parsed.unwrap_or_else(|| parse_cues(at, block, self.options));
+        for result in parsed {
+            let (cue_at, mut cue, mut kinds) = result?;
+            cue.notes = std::mem::take(&mut self.notes);
+            if let Some(previous) = track.cues.last() {
+                kinds.extend(warning::check_order(previous, &cue));
+            }
+            self.warnings.extend(kinds.into_iter().map(|kind| Warning {
+                line: cue_at.line,
+                kind,
+            }));
+            track.cues.push(cue);
+            let max_cues = self.options.limits.max_cues;
+            if let Some(max) = max_cues.filter(|max| track.cues.len() > *max) {
+                return Err(ParseError::new(ErrorKind::TooManyCues(max), cue_at));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Track {
+        self.finish_with_warnings().0
+    }
+
+    // Notes after the last cue have nothing to precede, so they stay
+    // with the last cue instead of being lost
+    pub(crate) fn finish_with_warnings(mut self) -> (Track, Vec<Warning>) {
+        if let Some(last) = self.track.cues.last_mut() {
+            last.notes.append(&mut self.notes);
+        }
+        trace!(
+            debug,
+            format = ?self.track.format,
+            cues = self.track.cues.len(),
+            warnings = self.warnings.len(),
+            "track parsed"
+        );
+        (self.track, self.warnings)
+    }
+}
+
+// Collects lines read from a stream into blank-line separated blocks,
+// yielding each block with the position it starts at
+#[derive(Default)]
+pub(crate) struct LineBlocks {
+    block: String,
+    start: Span,
+    line: usize,
+    offset: usize,
+    // largest block accepted, in bytes
+    max_bytes: Option<usize>,
+}
+
+impl LineBlocks {
+    // Creates a collector failing on blocks larger than `max_bytes`
+    pub(crate) fn new(max_bytes: Option<usize>) -> LineBlocks {
+        LineBlocks {
+            max_bytes,
+            ..LineBlocks::default()
+        }
+    }
+
+    // Adds a line (with or without its line ending), returning the
+    // previous block if this line ends it. Fails as soon as the block
+    // being collected grows past the limit, so input without blank lines
+    // can't grow it forever.
+    pub(crate) fn push_line(&mut self, line: &str) -> Result<Option<(Span, String)>, ParseError> {
+        self.line += 1;
+        let at = Span {
+            offset: self.offset,
+            line: self.line,
+            column: 1,
+        };
+        self.offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            return Ok(self.finish());
+        }
+        if self.block.is_empty() {
+            self.start = at;
+        } else {
+            self.block.push('\n');
+        }
+        self.block.push_str(line);
+        if exceeds(self.block.len(), self.max_bytes) {
+            self.block = String::new();
+            let max = self.max_bytes.unwrap();
+            return Err(ParseError::new(ErrorKind::InputTooLarge(max), at));
+        }
+        Ok(None)
+    }
+
+    // Returns the block still being collected, if any
+    pub(crate) fn finish(&mut self) -> Option<(Span, String)> {
+        if self.block.is_empty() {
+            return None;
+        }
+        Some((self.start, std::mem::take(&mut self.block)))
+    }
+}
+
+/// Lazily parses the cues of an SRT or WebVTT document one block at a
+/// time, so large documents can be processed without collecting every
+/// cue first
+///
+/// Unlike [`Track::parse`], `NOTE` blocks after the last cue are dropped,
+/// as there is no later cue to attach them to.
+///
+/// ```
+/// use title_parser::track::Cues;
+///
+/// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+/// let first = Cues::new(text).next().unwrap().unwrap();
+/// assert_eq!(first.text, "Hello");
+///
+/// let texts: Vec<String> = Cues::new(text).map(|cue| cue.unwrap().text).collect();
+/// assert_eq!(texts, vec!["Hello", "World"]);
+/// ```
+pub struct Cues<'a> {
+    blocks: Blocks<'a>,
+    format: Format,
+    options: ParseOptions,
+    // where `blocks` starts in the input, past any byte order mark
+    origin: Span,
+    pending: VecDeque<(Span, &'a str)>,
+    // notes waiting for the cue they precede
+    notes: Vec<String>,
+    count: usize,
+    done: bool,
+}
+
+impl<'a> Cues<'a> {
+    /// Creates an iterator over the cues of a document
+    pub fn new(input: &'a str) -> Cues<'a> {
+        Cues::with_options(input, &ParseOptions::default())
+    }
+
+    /// Creates an iterator over the cues of a document using the given
+    /// options
+    ///
+    /// `Limits::max_input_bytes` applies to each block, since the
+    /// document as a whole is never measured.
+    pub fn with_options(input: &'a str, options: &ParseOptions) -> Cues<'a> {
+        let (origin, input) = strip_bom(Span::default(), input);
+        let mut blocks = Blocks::new(input);
+        let format = detect_format(input);
+        if format == Format::WebVtt {
+            blocks.next();
+        }
+        Cues {
+            blocks,
+            format,
+            options: options.clone(),
+            origin,
+            pending: VecDeque::new(),
+            notes: Vec::new(),
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Format of the document being parsed
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
+
+impl<'a> Cues<'a> {
+    /// Turns this into an iterator over [`BorrowedCue`]s, whose text
+    /// borrows from the document where possible
+    ///
+    /// Borrowed cues carry no notes, so `NOTE` blocks are dropped.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use title_parser::track::Cues;
+    ///
+    /// let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+    /// let cue = Cues::new(text).borrowed().next().unwrap().unwrap();
+    /// assert!(matches!(cue.text, Cow::Borrowed("Hello")));
+    /// ```
+    pub fn borrowed(self) -> BorrowedCues<'a> {
+        BorrowedCues { cues: self }
+    }
+
+    // Finds the next cue, returning its text and where it starts
+    fn next_cue(&mut self) -> Option<Result<(Span, &'a str), ParseError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            if self.pending.is_empty() {
+                let Some((at, block)) = self.blocks.next() else {
+                    self.done = true;
+                    return None;
+                };
+                if self.format == Format::WebVtt && is_vtt_extra_block(block) {
+                    self.notes.extend(note_text(block));
+                    continue;
+                }
+                let at = at.within(self.origin);
+                self.pending = split_cues(block, self.options.mode)
+                    .into_iter()
+                    .map(|(offset, cue)| (Span::locate(block, offset).within(at), cue))
+                    .collect();
+            }
+            let (at, block) = self.pending.pop_front()?;
+            self.count += 1;
+            let max_cues = self.options.limits.max_cues;
+            if let Some(max) = max_cues.filter(|max| self.count > *max) {
+                self.done = true;
+                return Some(Err(ParseError::new(ErrorKind::TooManyCues(max), at)));
+            }
+            return Some(Ok((at, block)));
+        }
+    }
+}
+
+impl<'a> Iterator for Cues<'a> {
+    type Item = Result<Cue, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (at, block) = match self.next_cue()? {
+            Ok(found) => found,
+            Err(e) => return Some(Err(e)),
+        };
+        let cue = normalize_newlines(block)
+            .to_cue_with(&self.options)
+            .map(|mut cue| {
+                cue.notes = std::mem::take(&mut self.notes);
+                cue
+            })
+            .map_err(|e| relocate(e, block, at));
+        Some(cue)
+    }
+}
+
+/// Lazily parses the cues of a document as [`BorrowedCue`]s, see
+/// [`Cues::borrowed`]
+pub struct BorrowedCues<'a> {
+    cues: Cues<'a>,
+}
+
+impl<'a> Iterator for BorrowedCues<'a> {
+    type Item = Result<BorrowedCue<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (at, block) = match self.cues.next_cue()? {
+            Ok(found) => found,
+            Err(e) => return Some(Err(e)),
+        };
+        let options = &self.cues.options;
+        let cue = match normalize_newlines(block) {
+            Cow::Borrowed(block) => BorrowedCue::parse_with(block, options),
+            // text of CRLF documents lost its line endings, so no longer
+            // matches the document
+            Cow::Owned(normalized) => {
+                BorrowedCue::parse_with(&normalized, options).map(|cue| BorrowedCue {
+                    text: Cow::Owned(cue.text.into_owned()),
+                    ..cue
+                })
+            }
+        };
+        self.cues.notes.clear();
+        Some(cue.map_err(|e| relocate(e, block, at)))
+    }
+}
+
+// Splits a block into the cues it holds, along with the byte offset of
+// each within the block. Only lenient parsing looks for cues that are
+// missing the blank line before them, where a numeric line right
+// before a timing line is taken as the identifier of the next cue.
+pub(crate) fn split_cues(block: &str, mode: ParseMode) -> Vec<(usize, &str)> {
+    if mode == ParseMode::Strict {
+        return vec![(0, block)];
+    }
+    let lines: Vec<&str> = block.split('\n').collect();
+    let timings: Vec<usize> = (0..lines.len())
+        .filter(|&i| is_timing_line(lines[i]))
+        .collect();
+    let mut starts = vec![0];
+    for pair in timings.windows(2) {
+        let (previous, timing) = (pair[0], pair[1]);
+        let identifier = lines[timing - 1].trim();
+        let is_identifier = timing - 1 > previous
+            && !identifier.is_empty()
+            && identifier.chars().all(|c| c.is_ascii_digit());
+        starts.push(if is_identifier { timing - 1 } else { timing });
+    }
+    // byte offset of the start of every line
+    let mut offsets = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    for line in &lines {
+        offsets.push(offset);
+        offset += line.len() + 1;
+    }
+    offsets.push(block.len() + 1);
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).map_or(lines.len(), |&next| next);
+            (offsets[start], &block[offsets[start]..offsets[end] - 1])
+        })
+        .collect()
+}
+
+// Removes a leading byte order mark left over from decoding, moving the
+// position `input` starts at past it
+pub(crate) fn strip_bom(at: Span, input: &str) -> (Span, &str) {
+    match input.strip_prefix('\u{feff}') {
+        Some(rest) => (Span::locate(input, 3).within(at), rest),
+        None => (at, input),
+    }
+}
+
+// Moves an error found while parsing the cue `text` starting at `at` to
+// its position in the document. The cue was parsed with normalized line
+// endings, so the offset is worked out again from the line and column.
+pub(crate) fn relocate(mut error: ParseError, text: &str, at: Span) -> ParseError {
+    let span = error.span;
+    let line_start: usize = text
+        .split('\n')
+        .take(span.line - 1)
+        .map(|line| line.len() + 1)
+        .sum();
+    let line_start = line_start.min(text.len());
+    let column: usize = text[line_start..]
+        .chars()
+        .take(span.column - 1)
+        .map(char::len_utf8)
+        .sum();
+    error.span = Span {
+        offset: line_start + column,
+        ..span
+    }
+    .within(at);
+    error
+}
+
+// Converts CRLF line endings to LF
+pub(crate) fn normalize_newlines(input: &str) -> Cow<'_, str> {
+    if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+// Returns Some if the input starts with a WebVTT signature, holding the
+// text that follows the signature on the same line
+pub(crate) fn vtt_signature(input: &str) -> Option<Option<&str>> {
+    let first = input.lines().next()?.strip_prefix("WEBVTT")?;
+    if first.is_empty() {
+        return Some(None);
+    }
+    if !first.starts_with([' ', '\t']) {
+        return None;
+    }
+    let description = first.trim();
+    Some(if description.is_empty() {
+        None
+    } else {
+        Some(description)
+    })
+}
+
+// NOTE, STYLE and REGION blocks carry no cue
+pub(crate) fn is_vtt_extra_block(block: &str) -> bool {
+    let first = block.lines().next().unwrap_or("");
+    ["NOTE", "STYLE", "REGION"].iter().any(|keyword| {
+        first
+            .strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+    })
+}
+
+// Returns the CSS held by a STYLE block, or nothing for other blocks
+fn style_css(block: &str) -> &str {
+    match block.split_once('\n') {
+        Some((first, css)) if first.trim_end() == "STYLE" => css,
+        _ => "",
+    }
+}
+
+// Returns the comment held by a NOTE block, if it has one
+pub(crate) fn note_text(block: &str) -> Option<String> {
+    let rest = block.strip_prefix("NOTE")?;
+    if !(rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r'])) {
+        return None;
+    }
+    let lines: Vec<&str> = rest.lines().map(str::trim).collect();
+    let note = lines.join("\n").trim().to_string();
+    if note.is_empty() {
+        None
+    } else {
+        Some(note)
+    }
+}
+
+// Iterator over the blank-line separated blocks of a document, yielding
+// the position each block starts at along with its text
+pub(crate) struct Blocks<'a> {
+    rest: &'a str,
+    line: usize,
+    // byte offset of `rest` in the document
+    offset: usize,
+}
+
+impl<'a> Blocks<'a> {
+    pub(crate) fn new(input: &'a str) -> Blocks<'a> {
+        Blocks {
+            rest: input,
+            line: 1,
+            offset: 0,
+        }
+    }
+
+    // Splits off the next line, returning it and the remaining input
+    fn split_line(&self, from: usize) -> (&'a str, usize) {
+        match self.rest[from..].find('\n') {
+            Some(i) => (&self.rest[from..from + i], from + i + 1),
+            None => (&self.rest[from..], self.rest.len()),
+        }
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = (Span, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // skip blank lines between blocks
+        let mut pos = 0;
+        loop {
+            if pos >= self.rest.len() {
+                self.rest = "";
+                return None;
+            }
+            let (line, next) = self.split_line(pos);
+            if !line.trim().is_empty() {
+                break;
+            }
+            pos = next;
+            self.line += 1;
+        }
+
+        let start = pos;
+        let start_line = self.line;
+        let mut end = pos;
+        while pos < self.rest.len() {
+            let (line, next) = self.split_line(pos);
+            if line.trim().is_empty() {
+                break;
+            }
+            end = pos + line.len();
+            pos = next;
+            self.line += 1;
+        }
+        let block = &self.rest[start..end];
+        let at = Span {
+            offset: self.offset + start,
+            line: start_line,
+            column: 1,
+        };
+        self.rest = &self.rest[pos..];
+        self.offset += pos;
+        Some((at, block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warning::WarningKind;
+    use crate::Limits;
+
+    #[test]
+    fn track_from_vtt() -> Result<(), Error> {
+        let track = Track::parse(include_str!("example.vtt"))?;
+        assert_eq!(track.format, Format::WebVtt);
+        assert_eq!(track.cues.len(), 3);
+        assert_eq!(
+            track.cues[2].text,
+            "[ Bats Screeching ]\nThey won't get in your hair. They're after the bugs."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn track_from_vtt_with_notes() -> Result<(), Error> {
+        let track = Track::parse(include_str!("jp.vtt"))?;
+        assert_eq!(track.description, None);
+        assert_eq!(track.cues.len(), 5);
+        assert_eq!(track.cues[0].start.as_str(), "00:00:13.916");
+        Ok(())
+    }
+
+    #[test]
+    fn track_from_srt_with_crlf() -> Result<(), Error> {
+        let track = Track::parse(include_str!("jp.srt"))?;
+        assert_eq!(track.format, Format::Srt);
+        assert_eq!(track.cues.len(), 5);
+        assert_eq!(
+            track.cues[4].text,
+            "イオリ・セイ ビルドストライク\n行きます！"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn track_cue_limit() {
+        let options = ParseOptions {
+            limits: Limits {
+                max_cues: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = Track::parse_with(include_str!("example.vtt"), &options).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TooManyCues(2));
+        assert_eq!(err.span.line, 12);
+    }
+
+    #[test]
+    fn lenient_splits_cues_missing_blank_lines() -> Result<(), Error> {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nHello\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n00:00:05,000 --> 00:00:06,000\n3\n";
+        let err = Track::parse(text).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingBlankLine);
+        assert_eq!(err.span.line, 5);
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let track = Track::parse_with(text, &options)?;
+        let texts: Vec<&str> = track.cues.iter().map(|cue| cue.text.as_str()).collect();
+        assert_eq!(texts, vec!["Hello", "World", "3"]);
+        let lazy: Vec<String> = Cues::with_options(text, &options)
+            .map(|cue| cue.map(|cue| cue.text))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lazy, texts);
+        Ok(())
+    }
+
+    #[test]
+    fn warnings_carry_line_numbers() -> Result<(), Error> {
+        let text = "WEBVTT\n\n00:00:05.000 --> 00:00:06.000 shade:dark\nOne\n\n00:00:01.000 --> 00:00:02.000\nTwo\n";
+        let (track, warnings) = Track::parse_with_warnings(text, &ParseOptions::default())?;
+        assert_eq!(track.cues.len(), 2);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning {
+                    line: 3,
+                    kind: WarningKind::UnknownSetting("shade:dark".to_string()),
+                },
+                Warning {
+                    line: 6,
+                    kind: WarningKind::OutOfOrder,
+                },
+            ]
+        );
+        let (_, warnings) =
+            Track::parse_with_warnings(include_str!("jp.vtt"), &ParseOptions::default())?;
+        assert!(warnings.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn clamp_to_live_window() -> Result<(), Error> {
+        use crate::timecode::TimeCodeTrait;
+        let mut track = Track::parse(include_str!("jp.srt"))?;
+        let start = "00:00:14.000".to_timecode()?;
+        let end = "00:00:20.000".to_timecode()?;
+        assert!(track.clamp_to(&end, &start).is_err());
+        let options = ClampOptions { drop_outside: true };
+        track.clamp_to_with(&start, &end, &options)?;
+        assert!(!track.cues.is_empty());
+        for cue in &track.cues {
+            assert!(cue.start.total_millis() >= 14_000);
+            assert!(cue.end.total_millis() <= 20_000);
+            assert!(cue.start.as_str().contains(','));
+            assert!(cue.end.as_str().contains(','));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_recovering_skips_bad_blocks() -> Result<(), ParseError> {
+        let mut text = String::from("WEBVTT\n\n");
+        for i in 0..100 {
+            let arrow = if i == 42 { "->" } else { "-->" };
+            text.push_str(&format!(
+                "00:00:{:02}.000 {} 00:00:{:02}.500\ncue {}\n\n",
+                i % 60,
+                arrow,
+                i % 60,
+                i
+            ));
+        }
+        let (track, skipped) = Track::parse_recovering(&text, &ParseOptions::default())?;
+        assert_eq!(track.cues.len(), 99);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 43);
+        assert_eq!(skipped[0].error.span.line, 2 + 42 * 3 + 1);
+        let options = ParseOptions {
+            limits: Limits {
+                max_cues: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(Track::parse_recovering(&text, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cues_continue_after_error() {
+        let input = "00:00:01.000 -> 00:00:02.000\nbad\n\n00:00:03.000 --> 00:00:04.000\ngood\n";
+        let mut cues = Cues::new(input);
+        assert!(cues.next().unwrap().is_err());
+        assert_eq!(cues.next().unwrap().unwrap().text, "good");
+        assert!(cues.next().is_none());
+    }
+
+    #[test]
+    fn track_from_reader_matches_parse() -> Result<(), Error> {
+        let input = include_str!("jp.srt");
+        let reader = std::io::BufReader::with_capacity(7, input.as_bytes());
+        let from_reader = Track::from_reader(reader)?;
+        let parsed = Track::parse(input)?;
+        assert_eq!(from_reader.cues.len(), parsed.cues.len());
+        for (a, b) in from_reader.cues.iter().zip(parsed.cues.iter()) {
+            assert_eq!(a.text, b.text);
+            assert_eq!(a.start, b.start);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn track_from_reader_line_numbers() {
+        let input = "WEBVTT\r\n\r\nNOTE hi\r\n\r\n00:00:01.000 -> 00:00:02.000\r\nbad\r\n";
+        let err = Track::from_reader(input.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 5, column 1: malformed timing arrow, expected -->"
+        );
+        assert_eq!(err.parse_error().unwrap().kind, ErrorKind::MissingArrow);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn track_from_async_reader_matches_parse() -> Result<(), Error> {
+        let input = include_str!("example.vtt");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let reader = tokio::io::BufReader::with_capacity(5, input.as_bytes());
+        let track = runtime.block_on(Track::from_async_reader(reader))?;
+        let parsed = Track::parse(input)?;
+        assert_eq!(track.cues.len(), parsed.cues.len());
+        assert_eq!(track.cues[1].text, parsed.cues[1].text);
+        Ok(())
+    }
+
+    #[test]
+    fn content_hash_is_stable() -> Result<(), Error> {
+        let track = Track::parse(include_str!("example.vtt"))?;
+        assert_eq!(track.content_hash(), 0xb34d_94a2_d64b_7ba5);
+        let mut edited = Track::parse(include_str!("example.vtt"))?;
+        edited.cues[1].text.push('!');
+        assert_ne!(track.content_hash(), edited.content_hash());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_as_checks_the_format() -> Result<(), ParseError> {
+        let options = ParseOptions::default();
+        let vtt = include_str!("example.vtt");
+        assert_eq!(Track::parse_auto(vtt, &options)?.cues.len(), 3);
+        assert!(Track::parse_as(vtt, Format::Srt, &options).is_err());
+        let srt = include_str!("jp.srt");
+        assert_eq!(detect_format(srt), Format::Srt);
+        assert_eq!(Track::parse_auto(srt, &options)?.format, Format::Srt);
+        let err = Track::parse_as("", Format::WebVtt, &options).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingSignature);
+        Ok(())
+    }
+
+    #[test]
+    fn track_from_bytes_errors_carry_spans() {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nHi\n\n2\n00:00:03,000 -> 00:00:04,000\nYo\n";
+        let at = text.find("00:00:03").unwrap();
+        let mut bytes = b"\xEF\xBB\xBF".to_vec();
+        bytes.extend_from_slice(text.as_bytes());
+        let err = Track::from_bytes(&bytes).unwrap_err();
+        let span = err.parse_error().unwrap().span;
+        assert_eq!((span.offset, span.line, span.column), (at + 3, 6, 1));
+        assert!(bytes[span.offset..].starts_with(b"00:00:03"));
+
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let err = Track::from_bytes(&bytes).unwrap_err();
+        let span = err.parse_error().unwrap().span;
+        assert_eq!((span.line, span.column), (6, 1));
+    }
+
+    #[test]
+    fn track_from_path_errors_carry_context() {
+        let name = format!(
+            "title_parser_track_from_path_errors_carry_context_{}",
+            std::process::id()
+        );
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let vtt = dir.join("not_really.vtt");
+        fs::write(&vtt, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+        let err = Track::from_path(&vtt).unwrap_err();
+        assert_eq!(err.path(), Some(vtt.as_path()));
+        assert_eq!(err.parse_error().unwrap().kind, ErrorKind::MissingSignature);
+        let srt = dir.join("broken.srt");
+        fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\nnope\n").unwrap();
+        assert_eq!(
+            Track::from_path(&srt).unwrap_err().to_string(),
+            format!("{}: line 5, column 1: not a valid cue", srt.display())
+        );
+    }
+
+    #[test]
+    fn track_with_bom() -> Result<(), Error> {
+        let input = "\u{feff}WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHello\n";
+        assert_eq!(Track::parse(input)?.format, Format::WebVtt);
+        assert_eq!(Cues::new(input).format(), Format::WebVtt);
+        assert_eq!(Track::from_reader(input.as_bytes())?.cues.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn error_spans_point_into_document() {
+        let input = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\nok\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000 x\r\n00:00:05,000 -> 00:00:06,000\r\n";
+        let expected = Span {
+            offset: 79,
+            line: 7,
+            column: 1,
+        };
+        let err = Track::parse(input).unwrap_err();
+        assert_eq!(
+            (err.kind, err.span),
+            (ErrorKind::MissingBlankLine, expected)
+        );
+        let err = Cues::new(input).nth(1).unwrap().unwrap_err();
+        assert_eq!(err.span, expected);
+    }
+
+    #[test]
+    fn blocks_line_numbers() {
+        let input = "\n\na\nb\n  \n\nc\n";
+        let blocks: Vec<(usize, usize, &str)> = Blocks::new(input)
+            .map(|(at, block)| (at.offset, at.line, block))
+            .collect();
+        assert_eq!(blocks, vec![(2, 3, "a\nb"), (10, 7, "c")]);
+    }
+}
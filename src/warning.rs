@@ -0,0 +1,77 @@
+//! non-fatal problems found while parsing a document
+//!
+//! Warnings never stop a parse, they point QC tooling at cues that are
+//! likely to display badly.
+
+use crate::Cue;
+
+/// Kind of problem found in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// the cue starts before the previous cue ends
+    Overlap,
+    /// the cue starts before the previous cue starts
+    OutOfOrder,
+    /// the timing line carries a cue setting the parser doesn't know,
+    /// holding the setting as written
+    UnknownSetting(String),
+}
+
+/// A non-fatal problem along with where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// (1-based) line number of the block the problem was found in
+    pub line: usize,
+    /// what the problem is
+    pub kind: WarningKind,
+}
+
+// WebVTT cue settings along with the SRT coordinate extension
+const KNOWN_SETTINGS: [&str; 10] = [
+    "vertical", "line", "position", "size", "align", "region", "X1", "X2", "Y1", "Y2",
+];
+
+// Compares a cue against the one before it
+pub(crate) fn check_order(previous: &Cue, cue: &Cue) -> Option<WarningKind> {
+    let start = cue.start.total_millis();
+    if start < previous.start.total_millis() {
+        Some(WarningKind::OutOfOrder)
+    } else if start < previous.end.total_millis() {
+        Some(WarningKind::Overlap)
+    } else {
+        None
+    }
+}
+
+// Returns the settings on the timing line of `block` that aren't known
+pub(crate) fn unknown_settings(block: &str) -> Vec<WarningKind> {
+    let Some(timing) = block.lines().find(|line| crate::is_timing_line(line)) else {
+        return Vec::new();
+    };
+    let Some((_, end)) = timing.split_once('>') else {
+        return Vec::new();
+    };
+    end.split_whitespace()
+        .skip(1)
+        .filter(|setting| {
+            let key = setting.split(':').next().unwrap_or("");
+            !KNOWN_SETTINGS.contains(&key)
+        })
+        .map(|setting| WarningKind::UnknownSetting(setting.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_settings_are_reported() {
+        let block = "1\n00:00:01.000 --> 00:00:02.000 align:start color:red\ntext";
+        assert_eq!(
+            unknown_settings(block),
+            vec![WarningKind::UnknownSetting("color:red".to_string())]
+        );
+        assert!(unknown_settings("00:00:01,000 --> 00:00:02,000 X1:10 Y1:20").is_empty());
+    }
+}
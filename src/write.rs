@@ -0,0 +1,153 @@
+//! writes tracks back out as SRT or WebVTT
+
+use crate::track::{Format, Track};
+use crate::Cue;
+use std::fmt::Write;
+
+impl Track {
+    /// Renders the track as an SRT or WebVTT document
+    ///
+    /// Cue text is written as parsed, so markup stripped while parsing
+    /// is not restored. WebVTT output keeps the header, writes the styles
+    /// as a `STYLE` block and each cue's notes as `NOTE` blocks before
+    /// it. SRT has neither styles nor comments, so both are left out.
+    ///
+    /// ```
+    /// use title_parser::track::{Format, Track};
+    ///
+    /// let text = "WEBVTT\n\nNOTE check this\n\n00:00:01.000 --> 00:00:02.000\n- Hello\n";
+    /// let track = Track::parse(text).unwrap();
+    /// assert_eq!(
+    ///     track.write(Format::WebVtt),
+    ///     "WEBVTT\n\nNOTE check this\n\n00:00:01.000 --> 00:00:02.000\nHello\n"
+    /// );
+    /// assert_eq!(
+    ///     track.write(Format::Srt),
+    ///     "1\n00:00:01,000 --> 00:00:02,000\nHello\n"
+    /// );
+    /// ```
+    pub fn write(&self, format: Format) -> String {
+        let mut out = String::new();
+        if format == Format::WebVtt {
+            out.push_str("WEBVTT");
+            if let Some(description) = &self.description {
+                out.push(' ');
+                out.push_str(description);
+            }
+            out.push('\n');
+            for line in &self.metadata {
+                out.push_str(line);
+                out.push('\n');
+            }
+            if !self.styles.is_empty() {
+                out.push_str("\nSTYLE\n");
+                out.push_str(&self.styles.to_vtt());
+            }
+        }
+        for (i, cue) in self.cues.iter().enumerate() {
+            if format == Format::WebVtt {
+                for note in &cue.notes {
+                    out.push('\n');
+                    write_note(&mut out, note);
+                }
+            }
+            if format == Format::WebVtt || i > 0 {
+                out.push('\n');
+            }
+            if format == Format::Srt {
+                let _ = writeln!(out, "{}", i + 1);
+            }
+            write_cue(&mut out, cue, format);
+        }
+        out
+    }
+}
+
+fn write_note(out: &mut String, note: &str) {
+    if note.contains('\n') {
+        out.push_str("NOTE\n");
+    } else {
+        out.push_str("NOTE ");
+    }
+    for line in note.lines() {
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+fn write_cue(out: &mut String, cue: &Cue, format: Format) {
+    let separator = if format == Format::Srt { ',' } else { '.' };
+    let _ = writeln!(
+        out,
+        "{} --> {}",
+        stamp(cue.start.total_millis(), separator),
+        stamp(cue.end.total_millis(), separator)
+    );
+    // a blank line would end the cue block early
+    for line in cue.text.lines().filter(|line| !line.trim().is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+// Formats milliseconds as `hh:mm:ss` followed by the separator and
+// milliseconds
+pub(crate) fn stamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseError;
+
+    #[test]
+    fn notes_round_trip() -> Result<(), ParseError> {
+        let track = Track::parse(include_str!("jp.vtt"))?;
+        assert_eq!(track.cues[0].notes.len(), 2);
+        assert!(track.cues[0].notes[1].ends_with("NOTE /SegmentIndex"));
+        let again = Track::parse(&track.write(Format::WebVtt))?;
+        assert_eq!(again.cues.len(), track.cues.len());
+        for (a, b) in again.cues.iter().zip(&track.cues) {
+            assert_eq!((&a.text, &a.notes), (&b.text, &b.notes));
+            assert_eq!(a.start, b.start);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn srt_round_trip() -> Result<(), ParseError> {
+        let track = Track::parse(include_str!("jp.srt"))?;
+        let again = Track::parse(&track.write(Format::Srt))?;
+        assert_eq!(again.content_hash(), track.content_hash());
+        Ok(())
+    }
+
+    #[test]
+    fn styles_round_trip() -> Result<(), ParseError> {
+        let text = "WEBVTT\n\nSTYLE\n::cue(.loud) {\n  font-weight: bold;\n}\n\n00:00:01.000 --> 00:00:02.000\nHi\n";
+        let track = Track::parse(text)?;
+        assert!(track.styles.get("loud").unwrap().bold);
+        assert_eq!(
+            track.write(Format::WebVtt),
+            "WEBVTT\n\nSTYLE\n::cue(.loud) { font-weight: bold; }\n\n00:00:01.000 --> 00:00:02.000\nHi\n"
+        );
+        assert!(!track.write(Format::Srt).contains("STYLE"));
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_notes_stay_with_last_cue() -> Result<(), ParseError> {
+        let text = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n\nNOTE\nfix the\nending\n";
+        let track = Track::parse(text)?;
+        assert_eq!(track.cues[0].notes, vec!["fix the\nending"]);
+        Ok(())
+    }
+}